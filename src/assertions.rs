@@ -0,0 +1,88 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use std::fs;
+
+use crate::select::Condition;
+
+/// Validates `body` against the JSON Schema at `schema_path`, reporting every violation.
+pub fn check_schema(schema_path: &str, body: &Value) -> Result<()> {
+    let raw = fs::read_to_string(schema_path)
+        .with_context(|| format!("read schema file {schema_path}"))?;
+    let schema: Value = serde_json::from_str(&raw)
+        .with_context(|| format!("invalid JSON in schema file {schema_path}"))?;
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|err| anyhow!("invalid JSON Schema {schema_path}: {err}"))?;
+
+    let violations: Vec<String> = validator
+        .iter_errors(body)
+        .map(|err| {
+            let location = err.instance_path.to_string();
+            let location = if location.is_empty() {
+                "/"
+            } else {
+                location.as_str()
+            };
+            format!("  {location}: {err}")
+        })
+        .collect();
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "response does not match {schema_path} ({} violation(s)):\n{}",
+        violations.len(),
+        violations.join("\n")
+    ))
+}
+
+/// Checks an `--assert-status` value: an exact code (`201`) or a class with trailing
+/// `x`s (`2xx`, `40x`).
+pub fn parse_status_pattern(raw: &str) -> Result<String, String> {
+    let pattern = raw.trim().to_ascii_lowercase();
+    let digits = pattern.trim_end_matches('x');
+    let valid = pattern.len() == 3
+        && digits.starts_with(['1', '2', '3', '4', '5'])
+        && digits.chars().all(|ch| ch.is_ascii_digit());
+    if valid {
+        Ok(pattern)
+    } else {
+        Err(format!("invalid status {raw:?} (expected e.g. 201 or 2xx)"))
+    }
+}
+
+/// Passes when `status` matches any of `patterns`.
+pub fn check_status(patterns: &[String], status: u16) -> Result<()> {
+    let actual = status.to_string();
+    let matches = |pattern: &String| {
+        pattern.len() == actual.len()
+            && pattern
+                .chars()
+                .zip(actual.chars())
+                .all(|(want, got)| want == 'x' || want == got)
+    };
+    if patterns.iter().any(matches) {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "expected status {}, got {status}",
+        patterns.join(" or ")
+    ))
+}
+
+/// Every `--assert` condition must hold for `body`; failures list the value found.
+pub fn check_conditions(conditions: &[Condition], body: &Value) -> Result<()> {
+    let failures: Vec<String> = conditions
+        .iter()
+        .filter(|condition| !condition.matches(body))
+        .map(|condition| format!("  {}: got {}", condition.expr(), condition.observed(body)))
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} of {} assertion(s) failed:\n{}",
+        failures.len(),
+        conditions.len(),
+        failures.join("\n")
+    ))
+}
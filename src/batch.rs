@@ -0,0 +1,112 @@
+use anyhow::{Result, anyhow};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Runs `job` over `items` on `concurrency` threads and hands the results to `emit` in
+/// input order. A result is held back only until every earlier item has finished, so
+/// output still streams while staying diffable between runs.
+pub fn run_ordered<T, R>(
+    items: &[T],
+    concurrency: usize,
+    job: impl Fn(&T) -> R + Sync,
+    emit: impl FnMut(R) -> Result<()> + Send,
+) -> Result<()>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    // (index of the next result to emit, finished results waiting on earlier ones, emit)
+    let pending = Mutex::new((0_usize, BTreeMap::new(), emit));
+    let worker = || -> Result<()> {
+        loop {
+            let idx = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(idx) else {
+                return Ok(());
+            };
+            let result = job(item);
+            let mut guard = pending
+                .lock()
+                .map_err(|_| anyhow!("batch output lock poisoned"))?;
+            let (emitted, waiting, emit) = &mut *guard;
+            waiting.insert(idx, result);
+            while let Some(result) = waiting.remove(&*emitted) {
+                *emitted += 1;
+                emit(result)?;
+            }
+        }
+    };
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..concurrency.max(1))
+            .map(|_| scope.spawn(worker))
+            .collect();
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .map_err(|_| anyhow!("batch worker panicked"))?
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_tree::Operation;
+    use crate::http::{ClientOptions, HttpClient};
+    use crate::test_server::{Reply, TestServer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn emits_in_input_order_whatever_order_responses_arrive_in() {
+        // Each request sleeps a pseudo-random 0-39ms so later lines often finish first.
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos() as u64;
+        let server = TestServer::start(move |req| {
+            let id: u64 = req.target.rsplit('/').next().unwrap().parse().unwrap();
+            let jitter = (id + seed).wrapping_mul(6364136223846793005) >> 33;
+            std::thread::sleep(Duration::from_millis(jitter % 40));
+            Reply::json(200, &format!("{{\"id\":{id}}}"))
+        });
+        let client = HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &ClientOptions::default(),
+        )
+        .unwrap();
+        let op: Operation = serde_json::from_value(serde_json::json!({
+            "name": "get", "method": "GET", "path": "/items/{id}", "params": [], "has_body": false,
+        }))
+        .unwrap();
+
+        let ids: Vec<u64> = (0..24).collect();
+        let mut out = Vec::new();
+        run_ordered(
+            &ids,
+            8,
+            |id| {
+                let resp = client
+                    .execute(&op, &format!("/items/{id}"), &[], &[], None)
+                    .unwrap();
+                resp.body["id"].as_u64().unwrap()
+            },
+            |id| {
+                out.push(id);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(out, ids);
+        assert_eq!(server.requests().len(), ids.len());
+    }
+
+    #[test]
+    fn an_emit_error_stops_the_run() {
+        let items = [1, 2, 3];
+        let err = run_ordered(&items, 2, |n| *n, |_| Err(anyhow!("stdout closed"))).unwrap_err();
+        assert_eq!(err.to_string(), "stdout closed");
+    }
+}
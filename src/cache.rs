@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::{Map, Value, json};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, path::PathBuf};
+
+/// A stored 2xx GET response.
+pub struct Entry {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Value,
+    pub age: Duration,
+}
+
+/// Cache key for a request: SHA-256 of its parts, so file names stay the same across
+/// builds and Rust versions. The API key and request headers (sub-account, API version)
+/// are mixed in so accounts never share entries.
+pub fn key(
+    method: &str,
+    url: &str,
+    query: &[(String, String)],
+    api_key: &str,
+    headers: &[(&str, &str)],
+) -> String {
+    // JSON keeps the parts unambiguous (no `a` + `bc` vs `ab` + `c`).
+    let parts = json!([method, url, query, api_key, headers]);
+    Sha256::digest(parts.to_string().as_bytes()).iter().fold(
+        String::with_capacity(64),
+        |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        },
+    )
+}
+
+/// The entry for `key` if it is younger than `ttl`. Unreadable entries count as misses.
+pub fn get(key: &str, ttl: Duration) -> Option<Entry> {
+    let raw = fs::read_to_string(cache_dir()?.join(format!("{key}.json"))).ok()?;
+    let entry: Value = serde_json::from_str(&raw).ok()?;
+    let stored_at = entry.get("stored_at")?.as_u64()?;
+    let age = Duration::from_secs(now_secs().saturating_sub(stored_at));
+    if age > ttl {
+        return None;
+    }
+    let mut headers = HeaderMap::new();
+    for (name, value) in entry.get("headers")?.as_object()? {
+        if let (Ok(name), Some(Ok(value))) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            value.as_str().map(HeaderValue::from_str),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+    Some(Entry {
+        status: u16::try_from(entry.get("status")?.as_u64()?).ok()?,
+        headers,
+        body: entry.get("body")?.clone(),
+        age,
+    })
+}
+
+pub fn put(key: &str, status: u16, headers: &HeaderMap, body: &Value) -> Result<()> {
+    let dir = cache_dir().context("cannot locate the user cache dir")?;
+    fs::create_dir_all(&dir).with_context(|| format!("create {}", dir.display()))?;
+    let headers: Map<String, Value> = headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), json!(value.to_str().ok()?))))
+        .collect();
+    let entry = json!({
+        "stored_at": now_secs(),
+        "status": status,
+        "headers": headers,
+        "body": body,
+    });
+    let path = dir.join(format!("{key}.json"));
+    fs::write(&path, serde_json::to_string(&entry)?)
+        .with_context(|| format!("write {}", path.display()))
+}
+
+/// Deletes every entry, returning how many there were.
+pub fn clear() -> Result<usize> {
+    let Some(dir) = cache_dir() else {
+        return Ok(0);
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries.count(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err).with_context(|| format!("read {}", dir.display())),
+    };
+    fs::remove_dir_all(&dir).with_context(|| format!("remove {}", dir.display()))?;
+    Ok(entries)
+}
+
+/// `xendit/responses` under the platform cache dir (`$XDG_CACHE_HOME` or `~/.cache` on
+/// Linux, `~/Library/Caches` on macOS, `%LOCALAPPDATA%` on Windows).
+pub fn cache_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("xendit").join("responses"))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable_sha256_hex() {
+        let query = [("limit".to_string(), "10".to_string())];
+        let key = key(
+            "GET",
+            "https://api.xendit.co/balance",
+            &query,
+            "xnd_development_abc",
+            &[],
+        );
+        assert_eq!(key.len(), 64);
+        assert!(key.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        let input =
+            r#"["GET","https://api.xendit.co/balance",[["limit","10"]],"xnd_development_abc",[]]"#;
+        let expected = Sha256::digest(input.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        assert_eq!(key, expected);
+    }
+
+    #[test]
+    fn keys_separate_accounts_and_headers() {
+        let base = key("GET", "https://api.xendit.co/balance", &[], "key-a", &[]);
+        assert_ne!(
+            base,
+            key("GET", "https://api.xendit.co/balance", &[], "key-b", &[])
+        );
+        assert_ne!(
+            base,
+            key(
+                "GET",
+                "https://api.xendit.co/balance",
+                &[],
+                "key-a",
+                &[("for-user-id", "sub-1")]
+            )
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::{env, fs, path::PathBuf};
+
+use crate::select::Selector;
+
+/// Values captured from earlier responses (`--capture name=path`), persisted so that
+/// later invocations can reference them as `${name}`. The state file is only read once a
+/// reference or `--capture` needs it, so a damaged file can't break unrelated calls.
+#[derive(Debug, Default)]
+pub struct Captures {
+    loaded: OnceLock<Result<BTreeMap<String, String>, String>>,
+}
+
+impl Captures {
+    fn values(&self) -> Result<&BTreeMap<String, String>> {
+        self.loaded
+            .get_or_init(|| load().map_err(|err| format!("{err:#}")))
+            .as_ref()
+            .map_err(|err| anyhow!("{err}"))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = state_file().context("cannot locate state dir (HOME unset)")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self.values()?)?)
+            .with_context(|| format!("write {}", path.display()))
+    }
+
+    /// Stores each `name=path` spec's value from `body`; missing paths only warn. Paths use
+    /// the `--select` syntax.
+    pub fn record(&mut self, specs: &[String], body: &Value) -> Result<()> {
+        self.values()?;
+        let Some(Ok(values)) = self.loaded.get_mut() else {
+            unreachable!("capture state loaded above");
+        };
+        for spec in specs {
+            let (name, path) = parse_spec(spec)?;
+            let selector =
+                Selector::parse(path).with_context(|| format!("invalid --capture {spec:?}"))?;
+            match selector.apply(body) {
+                Some(Value::String(value)) => {
+                    values.insert(name.to_string(), value);
+                }
+                Some(value) => {
+                    values.insert(name.to_string(), value.to_string());
+                }
+                None => eprintln!("warning: capture {name}: nothing at {path}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces `${name}` references with captured values. `$${` is a literal `${`, and an
+    /// unknown name is left as written (with a warning) so literal payloads still work.
+    pub fn expand(&self, input: &str) -> Result<String> {
+        if !input.contains("${") {
+            return Ok(input.to_string());
+        }
+        let mut out = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find('}') else {
+                out.push_str(&rest[start..]);
+                return Ok(out);
+            };
+            let name = &after[..end];
+            match self.values()?.get(name) {
+                Some(value) => out.push_str(value),
+                None => {
+                    eprintln!(
+                        "warning: unknown capture ${{{name}}} left as is (write $${{ for a literal)"
+                    );
+                    out.push_str(&rest[start..start + 3 + end]);
+                }
+            }
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// Expands references inside every string of a JSON value.
+    pub fn expand_value(&self, value: &mut Value) -> Result<()> {
+        match value {
+            Value::String(text) => *text = self.expand(text)?,
+            Value::Array(items) => {
+                for item in items {
+                    self.expand_value(item)?;
+                }
+            }
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.expand_value(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn load() -> Result<BTreeMap<String, String>> {
+    let Some(path) = state_file() else {
+        return Ok(BTreeMap::new());
+    };
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    serde_json::from_str(&raw).with_context(|| format!("invalid capture state {}", path.display()))
+}
+
+fn parse_spec(spec: &str) -> Result<(&str, &str)> {
+    spec.split_once('=')
+        .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+        .ok_or_else(|| anyhow!("invalid --capture {spec:?} (expected name=path)"))
+}
+
+fn state_file() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(base.join("xendit").join("captures.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures(pairs: &[(&str, &str)]) -> Captures {
+        let values = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Captures {
+            loaded: OnceLock::from(Ok(values)),
+        }
+    }
+
+    #[test]
+    fn expands_known_names_and_keeps_escaped_and_unknown_ones() {
+        let captures = captures(&[("id", "pr-123")]);
+        assert_eq!(
+            captures
+                .expand("id=${id} raw=$${id} other=${nope}")
+                .unwrap(),
+            "id=pr-123 raw=${id} other=${nope}"
+        );
+    }
+
+    #[test]
+    fn text_without_references_never_reads_the_state_file() {
+        let captures = Captures::default();
+        assert_eq!(captures.expand("plain $ text").unwrap(), "plain $ text");
+        assert!(captures.loaded.get().is_none());
+    }
+
+    #[test]
+    fn records_values_with_select_paths() {
+        let mut captures = captures(&[]);
+        let body = serde_json::json!({"data": [{"id": "inv-1", "amount": 10000}]});
+        captures
+            .record(
+                &[
+                    "id=.data[0].id".to_string(),
+                    "amount=.data[-1].amount".to_string(),
+                ],
+                &body,
+            )
+            .unwrap();
+        assert_eq!(captures.expand("${id}/${amount}").unwrap(), "inv-1/10000");
+    }
+}
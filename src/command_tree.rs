@@ -1,20 +1,47 @@
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(dead_code)]
 pub struct CommandTree {
     pub version: u32,
     pub base_url: String,
+    /// Header that carries `--api-version`; absent means `api-version`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_header: Option<String>,
     pub resources: Vec<Resource>,
 }
 
+pub const DEFAULT_VERSION_HEADER: &str = "api-version";
+
+impl CommandTree {
+    pub fn version_header(&self) -> &str {
+        self.version_header
+            .as_deref()
+            .unwrap_or(DEFAULT_VERSION_HEADER)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(dead_code)]
 pub struct Resource {
     pub name: String,
+    /// Domain labels (e.g. `payments`) for grouping in `list --tag`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub ops: Vec<Operation>,
 }
 
+impl Resource {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own.eq_ignore_ascii_case(tag))
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[allow(dead_code)]
 pub struct Operation {
@@ -24,6 +51,54 @@ pub struct Operation {
     pub description: Option<String>,
     pub params: Vec<ParamDef>,
     pub has_body: bool,
+    /// `multipart` for form uploads (`--form`/`--file`); absent means a JSON body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_kind: Option<String>,
+    /// Content-Type for JSON bodies: `application/json` (default) or
+    /// `application/merge-patch+json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_content_type: Option<String>,
+    /// Documented rate limit for this operation in requests per second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<f64>,
+    /// Set when the endpoint is deprecated: the reason or replacement to mention, possibly
+    /// empty. Invoking the op prints it as a warning.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// Sample inputs for `--example-run`: objects with optional `params` and `body`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<serde_json::Value>,
+    /// Set when the JSON body declares an `external_id` field; enables `--auto-external-id`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external_id: bool,
+}
+
+impl Operation {
+    /// True for methods that only read state (GET/HEAD).
+    pub fn is_safe(&self) -> bool {
+        self.method.eq_ignore_ascii_case("GET") || self.method.eq_ignore_ascii_case("HEAD")
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.has_body && self.body_kind.as_deref() == Some("multipart")
+    }
+
+    /// JSON bodies are sent as an RFC 7396 merge patch.
+    pub fn is_merge_patch(&self) -> bool {
+        self.has_body
+            && !self.is_multipart()
+            && self.body_content_type.as_deref() == Some(MERGE_PATCH)
+    }
+
+    /// `deprecated`, or `deprecated: <reason>` when the tree gives one.
+    pub fn deprecation_note(&self) -> Option<String> {
+        let reason = self.deprecated.as_deref()?.trim();
+        Some(if reason.is_empty() {
+            "deprecated".to_string()
+        } else {
+            format!("deprecated: {reason}")
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -33,9 +108,233 @@ pub struct ParamDef {
     pub flag: String,
     pub location: String,
     pub required: bool,
+    /// Query params only: the flag repeats and each value becomes its own `key=value`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub multiple: bool,
+    /// Allowed values; flags reject anything else before a request is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<String>>,
+    /// Query params only: sent when no flag, param file or example supplies a value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// `integer`, `number` or `boolean`; absent means any string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<String>,
+    /// Regex values must match, e.g. `^[A-Za-z0-9_-]+$` (unanchored unless written so).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
+impl ParamDef {
+    pub fn value_type(&self) -> &str {
+        self.value_type.as_deref().unwrap_or("string")
+    }
+
+    /// Checks `raw` against `pattern` and `value_type`, returning the value to send.
+    /// Booleans are normalized to `true`/`false`; other values are passed through unchanged.
+    pub fn checked_value(&self, raw: &str) -> std::result::Result<String, String> {
+        if let Some(pattern) = &self.pattern {
+            let regex = Regex::new(pattern).map_err(|err| format!("invalid pattern: {err}"))?;
+            if !regex.is_match(raw) {
+                return Err(format!("expected a value matching {pattern}, got {raw:?}"));
+            }
+        }
+        let valid = match self.value_type() {
+            "integer" => raw.trim().parse::<i64>().is_ok(),
+            "number" => raw.trim().parse::<f64>().is_ok_and(f64::is_finite),
+            "boolean" => {
+                return match raw.trim().to_ascii_lowercase().as_str() {
+                    "true" | "1" | "yes" => Ok("true".to_string()),
+                    "false" | "0" | "no" => Ok("false".to_string()),
+                    _ => Err(format!("expected a boolean (true/false), got {raw:?}")),
+                };
+            }
+            _ => true,
+        };
+        if valid {
+            Ok(raw.to_string())
+        } else {
+            Err(format!("expected {}, got {raw:?}", self.value_type()))
+        }
+    }
 }
 
-pub fn load_command_tree() -> CommandTree {
+pub fn load_command_tree() -> Result<CommandTree> {
     let raw = include_str!("../schemas/command_tree.json");
-    serde_json::from_str(raw).expect("invalid command_tree.json")
+    serde_json::from_str(raw).context("invalid embedded command_tree.json")
+}
+
+/// Overrides query param defaults by param name or flag (e.g. a profile's `limit = "100"`).
+pub fn apply_param_defaults(tree: &mut CommandTree, defaults: &BTreeMap<String, String>) {
+    if defaults.is_empty() {
+        return;
+    }
+    let params = tree
+        .resources
+        .iter_mut()
+        .flat_map(|res| &mut res.ops)
+        .flat_map(|op| &mut op.params)
+        .filter(|param| param.location == "query");
+    for param in params {
+        if let Some(value) = defaults
+            .get(&param.name)
+            .or_else(|| defaults.get(&param.flag))
+        {
+            param.default = Some(value.clone());
+        }
+    }
+}
+
+const LOCATIONS: [&str; 3] = ["path", "query", "header"];
+const BODY_KINDS: [&str; 2] = ["json", "multipart"];
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+const BODY_CONTENT_TYPES: [&str; 2] = ["application/json", MERGE_PATCH];
+pub const VALUE_TYPES: [&str; 4] = ["string", "integer", "number", "boolean"];
+
+/// Catches authoring mistakes that deserialize fine but break at runtime. Every problem
+/// is reported, not just the first.
+pub fn validate_command_tree(tree: &CommandTree) -> Result<()> {
+    let mut problems = Vec::new();
+    let mut resources = HashSet::new();
+    for res in &tree.resources {
+        if !resources.insert(res.name.as_str()) {
+            problems.push(format!("duplicate resource {}", res.name));
+        }
+        if res.tags.iter().any(|tag| tag.trim().is_empty()) {
+            problems.push(format!("{}: empty tag", res.name));
+        }
+        let mut ops = HashSet::new();
+        for op in &res.ops {
+            let id = format!("{} {}", res.name, op.name);
+            if !ops.insert(op.name.as_str()) {
+                problems.push(format!("duplicate operation {id}"));
+            }
+            if op.method.is_empty() || reqwest::Method::from_bytes(op.method.as_bytes()).is_err() {
+                problems.push(format!("{id}: invalid method {:?}", op.method));
+            }
+            if let Some(kind) = &op.body_kind
+                && !BODY_KINDS.contains(&kind.as_str())
+            {
+                problems.push(format!(
+                    "{id}: invalid body_kind {kind:?} (expected json or multipart)"
+                ));
+            }
+            if let Some(content_type) = &op.body_content_type
+                && (!BODY_CONTENT_TYPES.contains(&content_type.as_str()) || op.is_multipart())
+            {
+                problems.push(format!(
+                    "{id}: invalid body_content_type {content_type:?} (expected {} for a JSON body)",
+                    BODY_CONTENT_TYPES.join(" or ")
+                ));
+            }
+            if op.external_id && (!op.has_body || op.is_multipart()) {
+                problems.push(format!("{id}: external_id is only valid for a JSON body"));
+            }
+            for param in &op.params {
+                if !LOCATIONS.contains(&param.location.as_str()) {
+                    problems.push(format!(
+                        "{id}: param {} has invalid location {:?} (expected path, query or header)",
+                        param.name, param.location
+                    ));
+                }
+                if param.location == "path" && !has_placeholder(&op.path, &param.name) {
+                    problems.push(format!(
+                        "{id}: path param {} does not appear in {}",
+                        param.name, op.path
+                    ));
+                }
+                if !VALUE_TYPES.contains(&param.value_type()) {
+                    problems.push(format!(
+                        "{id}: param {} has invalid value_type {:?} (expected {})",
+                        param.name,
+                        param.value_type(),
+                        VALUE_TYPES.join(", ")
+                    ));
+                    continue;
+                }
+                if let Some(pattern) = &param.pattern
+                    && let Err(err) = Regex::new(pattern)
+                {
+                    problems.push(format!(
+                        "{id}: param {} has invalid pattern {pattern:?}: {err}",
+                        param.name
+                    ));
+                    continue;
+                }
+                // Enums and defaults must themselves be valid values of the type and pattern.
+                let samples = param.enum_values.iter().flatten().chain(&param.default);
+                for sample in samples {
+                    if let Err(err) = param.checked_value(sample) {
+                        problems.push(format!("{id}: param {}: {err}", param.name));
+                    }
+                }
+            }
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "invalid command tree ({} problem(s)):\n  {}",
+        problems.len(),
+        problems.join("\n  ")
+    ))
+}
+
+/// Path params may be written `{name}`, `{{name}}` or `:name`.
+fn has_placeholder(path: &str, name: &str) -> bool {
+    if path.contains(&format!("{{{name}}}")) {
+        return true;
+    }
+    let colon = format!(":{name}");
+    path.match_indices(&colon).any(|(idx, _)| {
+        path[idx + colon.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '_'))
+    })
+}
+
+/// Loads a single `command_tree.json`, e.g. one regenerated from a newer spec.
+pub fn load_command_tree_file(path: &Path) -> Result<CommandTree> {
+    let raw = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("invalid command tree {}", path.display()))
+}
+
+/// Loads every `*.json` tree in `dir` (sorted by file name) and merges their resources.
+/// Resource names must be unique across files; base_url comes from the first file.
+pub fn load_command_tree_dir(dir: &Path) -> Result<CommandTree> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("read command tree dir {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    let mut merged: Option<CommandTree> = None;
+    let mut owners: HashMap<String, PathBuf> = HashMap::new();
+    for path in files {
+        let tree = load_command_tree_file(&path)?;
+        for res in &tree.resources {
+            if let Some(owner) = owners.insert(res.name.clone(), path.clone()) {
+                return Err(anyhow!(
+                    "resource {} defined in both {} and {}",
+                    res.name,
+                    owner.display(),
+                    path.display()
+                ));
+            }
+        }
+        match merged.as_mut() {
+            None => merged = Some(tree),
+            Some(acc) => {
+                acc.version = acc.version.max(tree.version);
+                acc.resources.extend(tree.resources);
+            }
+        }
+    }
+
+    let mut tree = merged.ok_or_else(|| anyhow!("no *.json command trees in {}", dir.display()))?;
+    tree.resources.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tree)
 }
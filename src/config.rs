@@ -0,0 +1,507 @@
+use anyhow::{Context, Result, anyhow};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::{env, fs, path::PathBuf};
+
+/// `~/.config/xendit/config.toml`:
+///
+/// ```toml
+/// default_profile = "sandbox"
+///
+/// [environments.production]
+/// api_key = "xnd_production_..."
+///
+/// [aliases]
+/// pr-get = "v3 get-payment-request --pretty"
+///
+/// [policy.patient]
+/// timeout_secs = 120
+/// retries = 5
+/// retry_backoff_ms = 2000
+///
+/// [profiles.live]
+/// api_key = "xnd_production_..."
+/// production = true
+///
+/// [profiles.sandbox]
+/// api_key = "xnd_development_..."
+/// base_url = "https://api.xendit.co"
+/// api_version = "2022-07-31"
+/// flags = ["--retries=3", "--pretty"]
+///
+/// [profiles.sandbox.param_defaults]
+/// limit = "100"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+    /// Keys and base URLs per `--env`, shared by every profile.
+    #[serde(default)]
+    environments: BTreeMap<String, Environment>,
+    /// Shared by every profile: alias token to the words it expands to.
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    /// Named timeout/retry bundles selected with `--policy`.
+    #[serde(default)]
+    policy: BTreeMap<String, Policy>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Profile {
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    /// Default for `--api-version`.
+    pub api_version: Option<String>,
+    /// `false` stops recording invocations in the history log (like `--no-history`).
+    pub history: Option<bool>,
+    /// Treated like `--env production`: a banner on every request and confirmations.
+    pub production: Option<bool>,
+    /// Global flags applied before the command line, which still wins.
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// Query param defaults by name or flag, overriding the command tree's.
+    #[serde(default)]
+    pub param_defaults: BTreeMap<String, String>,
+    /// The `--env` selection, layered above `XENDIT_API_KEY`/`XENDIT_API_URL`.
+    #[serde(skip)]
+    pub environment: Option<Environment>,
+    /// The `--policy` selection; explicit flags (and `XENDIT_TIMEOUT`) still win.
+    #[serde(skip)]
+    pub policy: Option<Policy>,
+}
+
+impl Profile {
+    /// `production = true`, or `--env production`.
+    pub fn is_production(&self) -> bool {
+        self.production == Some(true)
+            || self
+                .environment
+                .as_ref()
+                .is_some_and(Environment::is_production)
+    }
+}
+
+pub const ENVIRONMENTS: [&str; 2] = ["sandbox", "production"];
+
+/// Keys `check` accepts; everything else is reported as unknown.
+const TOP_LEVEL_KEYS: [&str; 5] = [
+    "default_profile",
+    "profiles",
+    "environments",
+    "aliases",
+    "policy",
+];
+const PROFILE_KEYS: [&str; 7] = [
+    "api_key",
+    "base_url",
+    "api_version",
+    "history",
+    "production",
+    "flags",
+    "param_defaults",
+];
+const ENVIRONMENT_KEYS: [&str; 2] = ["api_key", "base_url"];
+const POLICY_KEYS: [&str; 3] = ["timeout_secs", "retries", "retry_backoff_ms"];
+
+/// Flags read from the real command line before the config is loaded, so a profile's
+/// `flags` can't set them.
+pub const EARLY_FLAGS: [&str; 7] = [
+    "--profile",
+    "--env",
+    "--policy",
+    "--schema",
+    "--command-tree-dir",
+    "--error-format",
+    "--config-check",
+];
+
+/// The early flag `arg` spells, as `--flag` or `--flag=value`.
+pub fn early_flag(arg: &str) -> Option<&'static str> {
+    EARLY_FLAGS.into_iter().find(|flag| {
+        arg.strip_prefix(flag)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('='))
+    })
+}
+
+/// `[environments.<name>]`: the key and base URL used under `--env <name>`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Environment {
+    #[serde(skip)]
+    pub name: String,
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+}
+
+/// `[policy.<name>]`: defaults for the timeout and retry flags under `--policy <name>`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Policy {
+    pub timeout_secs: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+}
+
+impl Environment {
+    pub fn is_production(&self) -> bool {
+        self.name == "production"
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ActiveProfile {
+    pub name: Option<String>,
+    pub path: Option<PathBuf>,
+    pub profile: Profile,
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// Loads the selected profile (`--profile`, else `default_profile`, else `default` if
+/// present) with the selected environment and policy. A missing config file is not an
+/// error; an unknown explicit profile or policy is.
+pub fn load(
+    selected: Option<&str>,
+    environment: Option<&str>,
+    policy: Option<&str>,
+) -> Result<ActiveProfile> {
+    let path = config_path();
+    let raw = match &path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(raw) => Some(raw),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        },
+        None => None,
+    };
+    let file: ConfigFile = match (&raw, &path) {
+        (Some(raw), Some(path)) => {
+            toml::from_str(raw).with_context(|| format!("invalid config {}", path.display()))?
+        }
+        _ => ConfigFile::default(),
+    };
+
+    let name = match selected {
+        Some(name) => Some(name.to_string()),
+        None => file.default_profile.clone().or_else(|| {
+            file.profiles
+                .contains_key("default")
+                .then(|| "default".to_string())
+        }),
+    };
+    let mut profile = match &name {
+        Some(name) => file
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| match &path {
+                Some(path) => anyhow!("unknown profile {name} in {}", path.display()),
+                None => anyhow!("unknown profile {name} (no config dir)"),
+            })?,
+        None => Profile::default(),
+    };
+    if let Some(flag) = profile.flags.iter().find_map(|arg| early_flag(arg)) {
+        return Err(anyhow!(
+            "profile {} sets {flag} in flags, which is read before profiles load; pass it on \
+             the command line instead",
+            name.as_deref().unwrap_or_default()
+        ));
+    }
+    if let Some(env_name) = environment {
+        if !ENVIRONMENTS.contains(&env_name) {
+            return Err(anyhow!(
+                "unknown environment {env_name} (expected one of: {})",
+                ENVIRONMENTS.join(", ")
+            ));
+        }
+        let mut settings = file.environments.get(env_name).cloned().unwrap_or_default();
+        settings.name = env_name.to_string();
+        profile.environment = Some(settings);
+    }
+    if let Some(policy) = policy {
+        let settings = file
+            .policy
+            .get(policy)
+            .cloned()
+            .ok_or_else(|| match &path {
+                Some(path) => anyhow!("unknown policy {policy} in {}", path.display()),
+                None => anyhow!("unknown policy {policy} (no config dir)"),
+            })?;
+        profile.policy = Some(settings);
+    }
+    if let Some(alias) = file.aliases.keys().find(|alias| alias.starts_with('-')) {
+        return Err(anyhow!("alias {alias:?} must not start with '-'"));
+    }
+    for (alias, words) in &file.aliases {
+        if let Some(flag) = words.split_whitespace().find_map(early_flag) {
+            return Err(anyhow!(
+                "alias {alias} sets {flag}, which is read before aliases expand; pass it on the \
+                 command line instead"
+            ));
+        }
+    }
+    Ok(ActiveProfile {
+        name,
+        path,
+        profile,
+        aliases: file.aliases,
+    })
+}
+
+/// `--config-check`: every problem in a config file's text, each prefixed with the key
+/// it concerns. `load` only ignores unknown keys, so typos surface here instead.
+pub fn check(raw: &str) -> Vec<String> {
+    let table: toml::Table = match toml::from_str(raw) {
+        Ok(table) => table,
+        Err(err) => return vec![err.to_string().trim_end().to_string()],
+    };
+    let mut problems = Vec::new();
+    for (key, value) in &table {
+        match key.as_str() {
+            "default_profile" => {
+                if let Some(name) = value.as_str() {
+                    let profiles = table.get("profiles").and_then(toml::Value::as_table);
+                    if !profiles.is_some_and(|profiles| profiles.contains_key(name)) {
+                        problems.push(format!("default_profile: no profile named {name:?}"));
+                    }
+                } else {
+                    problems.push("default_profile: expected a string".to_string());
+                }
+            }
+            "profiles" => check_sections(key, value, &PROFILE_KEYS, &mut problems, check_profile),
+            "environments" => check_sections(
+                key,
+                value,
+                &ENVIRONMENT_KEYS,
+                &mut problems,
+                check_environment,
+            ),
+            "policy" => check_sections(key, value, &POLICY_KEYS, &mut problems, check_policy),
+            "aliases" => match value.as_table() {
+                Some(aliases) => {
+                    for (alias, words) in aliases {
+                        if alias.starts_with('-') {
+                            problems.push(format!("aliases.{alias}: must not start with '-'"));
+                        }
+                        match words.as_str() {
+                            Some(words) => problems.extend(
+                                words.split_whitespace().filter_map(early_flag).map(|flag| {
+                                    format!(
+                                        "aliases.{alias}: {flag} must be passed on the command line"
+                                    )
+                                }),
+                            ),
+                            None => problems.push(format!("aliases.{alias}: expected a string")),
+                        }
+                    }
+                }
+                None => problems.push("aliases: expected a table".to_string()),
+            },
+            _ => problems.push(unknown_key(key, &TOP_LEVEL_KEYS)),
+        }
+    }
+    problems
+}
+
+/// Checks each `[<key>.<name>]` table for unknown keys, then hands it to `check`.
+fn check_sections(
+    key: &str,
+    value: &toml::Value,
+    known: &[&str],
+    problems: &mut Vec<String>,
+    check: fn(&str, &toml::Value) -> Vec<String>,
+) {
+    let Some(sections) = value.as_table() else {
+        problems.push(format!("{key}: expected a table"));
+        return;
+    };
+    for (name, section) in sections {
+        let at = format!("{key}.{name}");
+        let Some(fields) = section.as_table() else {
+            problems.push(format!("{at}: expected a table"));
+            continue;
+        };
+        problems.extend(
+            fields
+                .keys()
+                .filter(|field| !known.contains(&field.as_str()))
+                .map(|field| unknown_key(&format!("{at}.{field}"), known)),
+        );
+        problems.extend(check(&at, section));
+    }
+}
+
+fn check_profile(at: &str, section: &toml::Value) -> Vec<String> {
+    let profile = match Profile::deserialize(section.clone()) {
+        Ok(profile) => profile,
+        Err(err) => return vec![format!("{at}: {}", err.message())],
+    };
+    let mut problems: Vec<String> = check_base_url(at, profile.base_url.as_deref())
+        .into_iter()
+        .collect();
+    problems.extend(
+        profile
+            .flags
+            .iter()
+            .filter(|flag| !flag.starts_with('-'))
+            .map(|flag| format!("{at}.flags: {flag:?} is not a flag")),
+    );
+    problems.extend(
+        profile
+            .flags
+            .iter()
+            .filter_map(|arg| early_flag(arg))
+            .map(|flag| format!("{at}.flags: {flag} must be passed on the command line")),
+    );
+    problems
+}
+
+fn check_environment(at: &str, section: &toml::Value) -> Vec<String> {
+    let name = at.trim_start_matches("environments.");
+    if !ENVIRONMENTS.contains(&name) {
+        return vec![format!(
+            "{at}: unknown environment (expected one of: {})",
+            ENVIRONMENTS.join(", ")
+        )];
+    }
+    match Environment::deserialize(section.clone()) {
+        Ok(env) => check_base_url(at, env.base_url.as_deref())
+            .into_iter()
+            .collect(),
+        Err(err) => vec![format!("{at}: {}", err.message())],
+    }
+}
+
+fn check_policy(at: &str, section: &toml::Value) -> Vec<String> {
+    match Policy::deserialize(section.clone()) {
+        Ok(_) => Vec::new(),
+        Err(err) => vec![format!("{at}: {}", err.message())],
+    }
+}
+
+fn unknown_key(at: &str, known: &[&str]) -> String {
+    let field = at.rsplit('.').next().unwrap_or(at);
+    match crate::suggest::closest(field, known.iter().copied(), 1).first() {
+        Some(suggestion) => format!("{at}: unknown key (did you mean {suggestion}?)"),
+        None => format!("{at}: unknown key"),
+    }
+}
+
+fn check_base_url(at: &str, base_url: Option<&str>) -> Option<String> {
+    let base_url = base_url?;
+    match Url::parse(base_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => None,
+        Ok(_) => Some(format!("{at}.base_url: {base_url:?} is not an http(s) URL")),
+        Err(err) => Some(format!("{at}.base_url: {base_url:?}: {err}")),
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("xendit").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_valid_config_has_no_problems() {
+        let raw = r#"
+            default_profile = "sandbox"
+
+            [environments.production]
+            base_url = "https://api.xendit.co"
+
+            [aliases]
+            pr-get = "v3 get-payment-request"
+
+            [profiles.sandbox]
+            api_key = "xnd_development_abc"
+            flags = ["--retries=3"]
+        "#;
+        assert_eq!(check(raw), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let raw = r#"
+            default_profile = "staging"
+            colour = "red"
+
+            [environments.qa]
+            base_url = "https://qa.example"
+
+            [profiles.sandbox]
+            base_ur = "https://api.xendit.co"
+            base_url = "api.xendit.co"
+            history = "no"
+            flags = ["pretty"]
+        "#;
+        assert_eq!(
+            check(raw),
+            vec![
+                "colour: unknown key".to_string(),
+                "default_profile: no profile named \"staging\"".to_string(),
+                "environments.qa: unknown environment (expected one of: sandbox, production)"
+                    .to_string(),
+                "profiles.sandbox.base_ur: unknown key (did you mean base_url?)".to_string(),
+                "profiles.sandbox: invalid type: string \"no\", expected a boolean".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_urls_and_flags_once_the_types_are_right() {
+        let raw = r#"
+            [profiles.sandbox]
+            base_url = "ftp://api.xendit.co"
+            flags = ["pretty"]
+        "#;
+        assert_eq!(
+            check(raw),
+            vec![
+                "profiles.sandbox.base_url: \"ftp://api.xendit.co\" is not an http(s) URL"
+                    .to_string(),
+                "profiles.sandbox.flags: \"pretty\" is not a flag".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn early_flags_are_rejected_in_profile_flags() {
+        let raw = r#"
+            [profiles.live]
+            flags = ["--env", "production", "--policy=ci", "--pretty"]
+        "#;
+        assert_eq!(
+            check(raw),
+            vec![
+                "profiles.live.flags: --env must be passed on the command line".to_string(),
+                "profiles.live.flags: --policy must be passed on the command line".to_string(),
+            ]
+        );
+        assert_eq!(early_flag("--environment"), None);
+    }
+
+    #[test]
+    fn early_flags_are_rejected_in_aliases() {
+        let raw = r#"
+            [aliases]
+            live-invoice = "--env production invoices create"
+            pr-get = "v3 get-payment-request --pretty"
+        "#;
+        assert_eq!(
+            check(raw),
+            vec!["aliases.live-invoice: --env must be passed on the command line".to_string()]
+        );
+    }
+
+    #[test]
+    fn a_syntax_error_is_reported_alone() {
+        let problems = check("[profiles.sandbox\napi_key = 1");
+        assert_eq!(problems.len(), 1);
+    }
+}
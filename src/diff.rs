@@ -0,0 +1,101 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use std::fs;
+
+/// One changed leaf, addressed with the same `.a.b[0]` syntax as `--capture`.
+pub struct Change {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// Diffs `current` against the baseline file. With `update`, the baseline is rewritten
+/// (or created) afterwards; a missing baseline without `update` is an error.
+pub fn against_baseline(path: &str, current: &Value, update: bool) -> Result<Vec<Change>> {
+    let changes = match fs::read_to_string(path) {
+        Ok(raw) => {
+            let baseline: Value = serde_json::from_str(&raw)
+                .with_context(|| format!("invalid JSON in baseline {path}"))?;
+            let mut changes = Vec::new();
+            diff_values("", &baseline, current, &mut changes);
+            changes
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && update => {
+            eprintln!("baseline {path} created");
+            Vec::new()
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!(
+                "baseline {path} not found (run with --update-baseline to create it)"
+            ));
+        }
+        Err(err) => return Err(err).with_context(|| format!("read baseline {path}")),
+    };
+
+    if update {
+        fs::write(path, serde_json::to_string_pretty(current)?)
+            .with_context(|| format!("write baseline {path}"))?;
+    }
+    Ok(changes)
+}
+
+pub fn changes_to_json(changes: &[Change]) -> Value {
+    Value::Array(
+        changes
+            .iter()
+            .map(|change| json!({"path": change.path, "old": change.old, "new": change.new}))
+            .collect(),
+    )
+}
+
+pub fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_item) in old_map {
+                let child = format!("{path}.{key}");
+                match new_map.get(key) {
+                    Some(new_item) => diff_values(&child, old_item, new_item, changes),
+                    None => changes.push(Change {
+                        path: child,
+                        old: Some(old_item.clone()),
+                        new: None,
+                    }),
+                }
+            }
+            for (key, new_item) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(Change {
+                        path: format!("{path}.{key}"),
+                        old: None,
+                        new: Some(new_item.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for idx in 0..old_items.len().max(new_items.len()) {
+                let child = format!("{path}[{idx}]");
+                match (old_items.get(idx), new_items.get(idx)) {
+                    (Some(old_item), Some(new_item)) => {
+                        diff_values(&child, old_item, new_item, changes)
+                    }
+                    (old_item, new_item) => changes.push(Change {
+                        path: child,
+                        old: old_item.cloned(),
+                        new: new_item.cloned(),
+                    }),
+                }
+            }
+        }
+        _ if old == new => {}
+        _ => changes.push(Change {
+            path: if path.is_empty() {
+                ".".to_string()
+            } else {
+                path.to_string()
+            },
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::command_tree::{CommandTree, Operation, Resource};
+
+pub const FORMATS: [&str; 1] = ["markdown"];
+
+/// The whole tree as one Markdown document.
+pub fn single_page(tree: &CommandTree) -> String {
+    let mut lines = header(tree);
+    for res in &tree.resources {
+        lines.push(String::new());
+        resource_lines(&mut lines, res, 2);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// One `<resource>.md` per resource plus an `index.md` linking them; returns the paths
+/// written. `dir` is created if missing.
+pub fn write_pages(tree: &CommandTree, dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    let mut index = header(tree);
+    index.push(String::new());
+    let mut written = Vec::new();
+    for res in &tree.resources {
+        let file = format!("{}.md", res.name);
+        index.push(format!(
+            "- [{}]({file}) ({} operations)",
+            res.name,
+            res.ops.len()
+        ));
+        let mut page = Vec::new();
+        resource_lines(&mut page, res, 1);
+        written.push(write_page(&dir.join(file), &page)?);
+    }
+    written.push(write_page(&dir.join("index.md"), &index)?);
+    Ok(written)
+}
+
+fn header(tree: &CommandTree) -> Vec<String> {
+    vec![
+        "# Xendit API reference".to_string(),
+        String::new(),
+        format!("Base URL: `{}`", tree.base_url),
+    ]
+}
+
+fn write_page(path: &Path, lines: &[String]) -> Result<PathBuf> {
+    fs::write(path, lines.join("\n") + "\n")
+        .with_context(|| format!("write {}", path.display()))?;
+    Ok(path.to_path_buf())
+}
+
+fn resource_lines(lines: &mut Vec<String>, res: &Resource, level: usize) {
+    lines.push(format!("{} {}", "#".repeat(level), res.name));
+    if !res.tags.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("Tags: {}", res.tags.join(", ")));
+    }
+    for op in &res.ops {
+        lines.push(String::new());
+        op_lines(lines, &res.name, op, level + 1);
+    }
+}
+
+fn op_lines(lines: &mut Vec<String>, resource: &str, op: &Operation, level: usize) {
+    lines.push(format!("{} {}", "#".repeat(level), op.name));
+    lines.push(String::new());
+    lines.push(format!("`{} {}`", op.method.to_ascii_uppercase(), op.path));
+    if let Some(reason) = op.deprecated.as_deref().map(str::trim) {
+        lines.push(String::new());
+        lines.push(if reason.is_empty() {
+            "**Deprecated.**".to_string()
+        } else {
+            format!("**Deprecated:** {reason}")
+        });
+    }
+    if let Some(desc) = op.description.as_deref().map(str::trim)
+        && !desc.is_empty()
+    {
+        lines.push(String::new());
+        lines.push(desc.to_string());
+    }
+
+    let mut usage = format!("xendit {resource} {}", op.name);
+    for param in op.params.iter().filter(|param| param.required) {
+        usage.push_str(&format!(" --{} <{}>", param.flag, param.name));
+    }
+    lines.extend([
+        String::new(),
+        "```bash".to_string(),
+        usage,
+        "```".to_string(),
+    ]);
+
+    if !op.params.is_empty() {
+        lines.extend([
+            String::new(),
+            "| Flag | Location | Required | Notes |".to_string(),
+            "|---|---|---|---|".to_string(),
+        ]);
+        for param in &op.params {
+            let mut notes = Vec::new();
+            if param.value_type() != "string" {
+                notes.push(param.value_type().to_string());
+            }
+            if param.multiple {
+                notes.push("repeatable".to_string());
+            }
+            if let Some(values) = &param.enum_values {
+                notes.push(format!("one of: {}", values.join(", ")));
+            }
+            if let Some(default) = &param.default {
+                notes.push(format!("default: {default}"));
+            }
+            if let Some(pattern) = &param.pattern {
+                notes.push(format!("pattern: `{pattern}`"));
+            }
+            lines.push(format!(
+                "| `--{}` | {} | {} | {} |",
+                param.flag,
+                param.location,
+                if param.required { "yes" } else { "no" },
+                table_cell(&notes.join("; "))
+            ));
+        }
+    }
+
+    let body = if op.is_multipart() {
+        "multipart (`--form NAME=VALUE`, `--file NAME=@PATH`)"
+    } else if op.is_merge_patch() {
+        "JSON merge patch (`--body`, `--field`, `--merge`)"
+    } else if op.has_body {
+        "JSON (`--body`, `--field`)"
+    } else {
+        "none"
+    };
+    lines.push(String::new());
+    lines.push(format!("Body: {body}"));
+}
+
+/// Escapes text for a Markdown table cell.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+use std::{env, fs};
+
+/// Opens `$VISUAL`/`$EDITOR` on a temp file seeded with `initial` and returns the saved JSON.
+/// Invalid JSON reopens the editor; saving an empty file aborts.
+pub fn edit_json(initial: &str) -> Result<Value> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = env::temp_dir().join(format!("xendit-body-{}.json", std::process::id()));
+    fs::write(&path, initial).with_context(|| format!("write {}", path.display()))?;
+
+    let result = edit_loop(&editor, &path);
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn edit_loop(editor: &str, path: &Path) -> Result<Value> {
+    loop {
+        // Run through the shell so EDITOR values with arguments (e.g. `code --wait`) work.
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg("sh")
+            .arg(path)
+            .status()
+            .with_context(|| format!("launch editor `{editor}`"))?;
+        if !status.success() {
+            return Err(anyhow!("editor `{editor}` failed: {status}"));
+        }
+
+        let raw = fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        if raw.trim().is_empty() {
+            return Err(anyhow!("empty body, aborting"));
+        }
+        match serde_json::from_str(&raw) {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                eprintln!(
+                    "invalid JSON body: {err}; reopening editor (save an empty file to abort)"
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use serde_json::Value;
+
+/// Common Xendit `error_code` values with a plain-language explanation and suggested fix.
+const EXPLANATIONS: &[(&str, &str, &str)] = &[
+    (
+        "API_VALIDATION_ERROR",
+        "the request failed Xendit's input validation",
+        "check `message`/`errors` in the response for the offending field",
+    ),
+    (
+        "INVALID_JSON_FORMAT",
+        "the request body is not valid JSON",
+        "validate the payload (try --dump-parsed-body)",
+    ),
+    (
+        "INVALID_API_KEY",
+        "the API key is malformed, revoked, or for another account",
+        "check XENDIT_API_KEY / --api-key",
+    ),
+    (
+        "REQUEST_FORBIDDEN_ERROR",
+        "the API key lacks permission for this endpoint",
+        "grant the permission to the key in the Xendit dashboard",
+    ),
+    (
+        "DUPLICATE_ERROR",
+        "a resource with this external id already exists",
+        "use a different external_id / reference_id",
+    ),
+    (
+        "IDEMPOTENCY_ERROR",
+        "the idempotency key was already used with a different payload",
+        "reuse the key only for identical retries, otherwise generate a new one",
+    ),
+    (
+        "DATA_NOT_FOUND",
+        "no resource with that id exists in this environment",
+        "check the id and whether you are using test vs live keys",
+    ),
+    (
+        "NOT_FOUND",
+        "the resource or endpoint does not exist",
+        "check the id and whether you are using test vs live keys",
+    ),
+    (
+        "CHANNEL_NOT_ACTIVATED",
+        "the payment channel is not activated for this account",
+        "activate the channel in the Xendit dashboard",
+    ),
+    (
+        "FEATURE_NOT_AVAILABLE",
+        "the feature is not enabled for this account",
+        "contact Xendit to enable it",
+    ),
+    (
+        "INSUFFICIENT_BALANCE",
+        "the account balance cannot cover this request",
+        "top up the balance or lower the amount",
+    ),
+    (
+        "RATE_LIMIT_EXCEEDED",
+        "too many requests in the current window",
+        "slow down (see --rate-limit / --adaptive-rate-limit)",
+    ),
+    (
+        "SERVER_ERROR",
+        "Xendit hit an internal error",
+        "retry later; contact Xendit support if it persists",
+    ),
+];
+
+pub fn explain(error_code: &str) -> Option<(&'static str, &'static str)> {
+    EXPLANATIONS
+        .iter()
+        .find(|(code, _, _)| code.eq_ignore_ascii_case(error_code))
+        .map(|(_, explanation, fix)| (*explanation, *fix))
+}
+
+/// Extracts `error_code` from a Xendit error body.
+pub fn error_code(body: &Value) -> Option<&str> {
+    body.get("error_code").and_then(Value::as_str)
+}
+
+/// Extracts the human-readable `message` from a Xendit error body.
+pub fn message(body: &Value) -> Option<&str> {
+    body.get("message").and_then(Value::as_str).map(str::trim)
+}
@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use std::{env, path::PathBuf};
+
+use crate::clock;
+use crate::command_tree::Operation;
+
+/// Appends one history line for an operation when dropped, so errors and early returns
+/// are logged too. Only the resource/op names from the command tree, method, status,
+/// duration and `--env` are written: never params, paths, headers, bodies or keys.
+pub struct Recorder {
+    entry: Option<Value>,
+    started: Instant,
+    /// Status and elapsed time once a response arrived.
+    outcome: Option<(Option<u16>, Duration)>,
+}
+
+impl Recorder {
+    /// A disabled recorder writes nothing.
+    pub fn start(resource: &str, op: &Operation, env: Option<&str>, enabled: bool) -> Self {
+        Self {
+            entry: enabled.then(|| {
+                json!({
+                    "resource": resource,
+                    "op": op.name,
+                    "method": op.method.to_ascii_uppercase(),
+                    "env": env,
+                })
+            }),
+            started: Instant::now(),
+            outcome: None,
+        }
+    }
+
+    /// Marks that a response arrived; `None` for a streamed body whose status isn't kept.
+    pub fn responded(&mut self, status: Option<u16>) {
+        self.outcome = Some((status, self.started.elapsed()));
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let Some(mut entry) = self.entry.take() else {
+            return;
+        };
+        let (status, elapsed) = self.outcome.unwrap_or((None, self.started.elapsed()));
+        entry["ts"] = json!(clock::now_rfc3339());
+        entry["status"] = json!(status);
+        entry["error"] = json!(self.outcome.is_none());
+        entry["duration_ms"] = json!(elapsed.as_millis() as u64);
+        // Fails open: a history problem must never affect the command itself.
+        if let Err(err) = append(&entry) {
+            eprintln!("warning: history not recorded: {err:#}");
+        }
+    }
+}
+
+fn append(entry: &Value) -> Result<()> {
+    let path = history_path().context("cannot locate state dir (HOME unset)")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("open {}", path.display()))?;
+    // One write per line, so concurrent `batch` workers can't interleave entries.
+    file.write_all(format!("{entry}\n").as_bytes())
+        .with_context(|| format!("write {}", path.display()))
+}
+
+/// The last `limit` entries, oldest first. Unparseable lines are skipped.
+pub fn read(limit: usize) -> Result<Vec<Value>> {
+    let Some(path) = history_path() else {
+        return Ok(Vec::new());
+    };
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    let entries: Vec<Value> = raw
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(limit);
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(base.join("xendit").join("history.jsonl"))
+}
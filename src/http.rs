@@ -1,69 +1,972 @@
-use anyhow::{Context, Result};
-use reqwest::blocking::{Client, RequestBuilder};
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+use reqwest::blocking::multipart::Form;
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
 use reqwest::header::HeaderMap;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Method, NoProxy, Proxy, Url};
 use serde_json::{Map, Value, json};
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cache;
+use crate::clock;
+use crate::command_tree::{MERGE_PATCH, Operation};
+use crate::errors;
+use crate::rate_limit::RateLimiter;
+use crate::redact::{self, RedactSet};
+
+/// Longest single backoff sleep between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// A request body: JSON, a JSON merge patch (RFC 7396), or `multipart/form-data` parts.
+#[derive(Debug, Clone)]
+pub enum Body {
+    Json(Value),
+    MergePatch(Value),
+    Multipart(Vec<FormPart>),
+}
+
+/// One form part: `--form name=value` or `--file name=@path`.
+#[derive(Debug, Clone)]
+pub enum FormPart {
+    Text { name: String, value: String },
+    File { name: String, path: PathBuf },
+}
+
+impl Body {
+    /// Content-Type announced in previews; reqwest adds the boundary when sending.
+    fn content_type(&self) -> &'static str {
+        match self {
+            Body::Json(_) => "application/json",
+            Body::MergePatch(_) => MERGE_PATCH,
+            Body::Multipart(_) => "multipart/form-data",
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Body::Json(value) | Body::MergePatch(value) => value.clone(),
+            Body::Multipart(parts) => {
+                let parts: Vec<Value> = parts
+                    .iter()
+                    .map(|part| match part {
+                        FormPart::Text { name, value } => json!({"name": name, "value": value}),
+                        FormPart::File { name, path } => {
+                            json!({"name": name, "file": path.display().to_string()})
+                        }
+                    })
+                    .collect();
+                json!({ "multipart": parts })
+            }
+        }
+    }
+}
+
+/// Built per attempt: a `Form` streams its files and can't be reused for a retry.
+fn build_form(parts: &[FormPart]) -> Result<Form> {
+    let mut form = Form::new();
+    for part in parts {
+        form = match part {
+            FormPart::Text { name, value } => form.text(name.clone(), value.clone()),
+            FormPart::File { name, path } => form
+                .file(name.clone(), path)
+                .with_context(|| format!("read {}", path.display()))?,
+        };
+    }
+    Ok(form)
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Value,
+    /// The body bytes as received (after decompression), for `--download`.
+    pub raw: Vec<u8>,
+    /// Number of attempts made, including retries; 0 when served from the cache.
+    pub attempts: u32,
+    /// Wall time from the first send attempt until the body was read, retries and 429
+    /// waits included; zero when served from the cache.
+    pub elapsed: Duration,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The `Content-Type` when it isn't JSON, so callers can flag text/binary bodies.
+    pub fn non_json_content_type(&self) -> Option<&str> {
+        let content_type = self
+            .headers
+            .get(reqwest::header::CONTENT_TYPE)?
+            .to_str()
+            .ok()?;
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        let json = mime.eq_ignore_ascii_case("application/json") || mime.ends_with("+json");
+        (!json).then_some(content_type)
+    }
+
+    /// The file name from `Content-Disposition`, reduced to its last path component.
+    pub fn attachment_filename(&self) -> Option<String> {
+        let disposition = self
+            .headers
+            .get(reqwest::header::CONTENT_DISPOSITION)?
+            .to_str()
+            .ok()?;
+        let mut plain = None;
+        for param in disposition.split(';').map(str::trim) {
+            if let Some(value) = param.strip_prefix("filename*=") {
+                // RFC 5987: `UTF-8''percent%20encoded`.
+                let encoded = value.split_once("''").map_or(value, |(_, rest)| rest);
+                let decoded = percent_decode_str(encoded).decode_utf8().ok()?;
+                return safe_file_name(&decoded);
+            }
+            if let Some(value) = param.strip_prefix("filename=") {
+                plain = safe_file_name(value.trim_matches('"'));
+            }
+        }
+        plain
+    }
+}
+
+fn safe_file_name(name: &str) -> Option<String> {
+    let name = Path::new(name).file_name()?.to_str()?;
+    (!name.starts_with('.')).then(|| name.to_string())
+}
+
+/// A completed request with a non-2xx status.
+#[derive(Debug)]
+pub struct StatusError {
+    pub status: u16,
+    pub attempts: u32,
+    /// Xendit `error_code` from the body.
+    pub code: Option<String>,
+    /// Xendit `message` from the body.
+    pub message: Option<String>,
+    /// `request-id`/`x-request-id` response header, for support tickets.
+    pub request_id: Option<String>,
+}
+
+impl StatusError {
+    pub fn from_response(resp: &HttpResponse) -> Self {
+        let request_id = ["request-id", "x-request-id"]
+            .iter()
+            .find_map(|name| resp.headers.get(*name)?.to_str().ok())
+            .map(str::to_string);
+        Self {
+            status: resp.status,
+            attempts: resp.attempts,
+            code: errors::error_code(&resp.body).map(str::to_string),
+            message: errors::message(&resp.body).map(str::to_string),
+            request_id,
+        }
+    }
+
+    /// `{"error": {...}}` for `--error-format json`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": {
+                "status": self.status,
+                "code": self.code,
+                "message": self.message,
+                "request_id": self.request_id,
+                "attempts": self.attempts,
+            }
+        })
+    }
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http {}", self.status)?;
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        match (&self.code, &self.message) {
+            (Some(code), Some(message)) => write!(f, ": {code}: {message}"),
+            (Some(detail), None) | (None, Some(detail)) => write!(f, ": {detail}"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// Xendit's scheme: the API key as the basic-auth username, empty password.
+    #[default]
+    Basic,
+    Bearer,
+    /// No `Authorization` header, e.g. for mock servers.
+    None,
+}
+
+impl AuthScheme {
+    pub const NAMES: [&'static str; 3] = ["basic", "bearer", "none"];
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "basic" => Ok(Self::Basic),
+            "bearer" => Ok(Self::Bearer),
+            "none" => Ok(Self::None),
+            other => Err(anyhow!("unknown auth scheme {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// Global client-side rate limit in requests per second.
+    pub rate_limit: Option<f64>,
+    /// Methods tunnelled as POST with `X-HTTP-Method-Override` for gateways that block them.
+    pub method_override: Vec<String>,
+    /// Pace requests using the server's `X-RateLimit-Remaining`/`X-RateLimit-Reset` headers.
+    pub adaptive_rate_limit: bool,
+    /// Extra attempts for transient failures (connection errors, 502/503/504).
+    pub retries: u32,
+    /// Retry mutating methods too (see `retryable`); GET/HEAD only otherwise.
+    pub retry_all: bool,
+    /// Base delay for exponential backoff between retries.
+    pub retry_backoff: Duration,
+    /// Total per-request timeout; `None` waits forever.
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// `-v` logs request/response lines and headers to stderr; `-vv` adds bodies.
+    pub verbose: u8,
+    /// Headers masked in verbose logs.
+    pub redact: RedactSet,
+    pub auth_scheme: AuthScheme,
+    /// Explicit proxy (`http://`, `https://` or `socks5://`); otherwise `HTTP_PROXY`,
+    /// `HTTPS_PROXY` and `NO_PROXY` apply.
+    pub proxy: Option<String>,
+    /// Skip TLS certificate verification (staging gateways with self-signed certs).
+    pub insecure: bool,
+    /// Extra PEM root certificate to trust.
+    pub cacert: Option<PathBuf>,
+    /// Wait out 429 responses (Retry-After, else backoff) instead of failing.
+    pub rate_limit_wait: bool,
+    pub max_rate_retries: u32,
+    /// Serve 2xx GET responses from the on-disk cache while younger than this.
+    pub cache_ttl: Option<Duration>,
+    /// Sub-account sent as `for-user-id` on every request unless a header already sets it.
+    pub for_user_id: Option<String>,
+    /// `(header, version)` pinning API behavior, e.g. `("api-version", "2022-07-31")`.
+    pub api_version: Option<(String, String)>,
+    /// Redirects to follow before failing; 0 returns the 3xx response as is.
+    pub max_redirects: usize,
+    /// Append one JSON line per retry or 429 wait to this file.
+    pub retry_log: Option<PathBuf>,
+}
 
 pub struct HttpClient {
     base_url: String,
     api_key: String,
     client: Client,
+    limiter: RateLimiter,
+    method_override: Vec<String>,
+    retries: u32,
+    retry_all: bool,
+    retry_backoff: Duration,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    verbose: u8,
+    redact: RedactSet,
+    auth_scheme: AuthScheme,
+    rate_limit_wait: bool,
+    max_rate_retries: u32,
+    cache_ttl: Option<Duration>,
+    /// `for-user-id` and API version headers, unless `headers` already sets them.
+    default_headers: Vec<(String, String)>,
+    /// `--retry-log` file, shared by `batch` workers.
+    retry_log: Option<Mutex<fs::File>>,
 }
 
 impl HttpClient {
-    pub fn new(base_url: String, api_key: String) -> Result<Self> {
-        let client = Client::builder()
+    pub fn new(base_url: String, api_key: String, opts: &ClientOptions) -> Result<Self> {
+        let mut builder = Client::builder()
             .user_agent("xendit-cli")
-            .build()
-            .context("build http client")?;
+            .timeout(opts.timeout)
+            .redirect(redirect_policy(opts.max_redirects, opts.verbose > 0));
+        if let Some(connect_timeout) = opts.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if opts.insecure {
+            eprintln!(
+                "WARNING: --insecure disables TLS certificate verification; responses may be intercepted"
+            );
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(path) = &opts.cacert {
+            let pem = fs::read(path).with_context(|| format!("read {}", path.display()))?;
+            let cert = Certificate::from_pem(&pem)
+                .with_context(|| format!("invalid PEM certificate {}", path.display()))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(url) = &opts.proxy {
+            let proxy = Proxy::all(url)
+                .with_context(|| format!("invalid --proxy url {url:?}"))?
+                .no_proxy(NoProxy::from_env());
+            builder = builder.proxy(proxy);
+        }
+        let client = builder.build().context("build http client")?;
+        let retry_log = match &opts.retry_log {
+            Some(path) => Some(Mutex::new(
+                fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("open --retry-log {}", path.display()))?,
+            )),
+            None => None,
+        };
         Ok(Self {
             base_url,
             api_key,
             client,
+            limiter: RateLimiter::new(opts.rate_limit, opts.adaptive_rate_limit, opts.verbose > 0),
+            method_override: opts.method_override.clone(),
+            retries: opts.retries,
+            retry_all: opts.retry_all,
+            retry_backoff: opts.retry_backoff,
+            timeout: opts.timeout,
+            connect_timeout: opts.connect_timeout,
+            verbose: opts.verbose,
+            redact: opts.redact.clone(),
+            auth_scheme: opts.auth_scheme,
+            rate_limit_wait: opts.rate_limit_wait,
+            max_rate_retries: opts.max_rate_retries,
+            cache_ttl: opts.cache_ttl,
+            default_headers: opts
+                .for_user_id
+                .iter()
+                .map(|id| (FOR_USER_ID_HEADER.to_string(), id.clone()))
+                .chain(opts.api_version.clone())
+                .collect(),
+            retry_log,
         })
     }
 
     pub fn execute(
         &self,
-        method: &str,
+        op: &Operation,
         path: &str,
         query: &[(String, String)],
-        body: Option<Value>,
-        raw: bool,
-        pretty: bool,
-    ) -> Result<(String, bool, u16)> {
-        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
-        let method = method.parse().context("invalid http method")?;
-        let mut req = self.client.request(method, url).basic_auth(&self.api_key, Some(""));
-        req = apply_query(req, query);
-        if let Some(value) = body {
-            req = req.json(&value);
+        headers: &[(String, String)],
+        body: Option<Body>,
+    ) -> Result<HttpResponse> {
+        let cache_key = self
+            .cache_ttl
+            .filter(|_| !self.tunnelled(op) && op.method.eq_ignore_ascii_case("GET"))
+            .map(|_| {
+                let sent: Vec<(&str, &str)> = self
+                    .implicit_headers(headers)
+                    .chain(headers)
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect();
+                cache::key("GET", &self.url(path), query, &self.api_key, &sent)
+            });
+        if let (Some(key), Some(ttl)) = (&cache_key, self.cache_ttl)
+            && let Some(entry) = cache::get(key, ttl)
+        {
+            if self.verbose > 0 {
+                eprintln!("cache hit ({}s old): GET {}", entry.age.as_secs(), op.path);
+            } else {
+                eprintln!("cache hit ({}s old)", entry.age.as_secs());
+            }
+            return Ok(HttpResponse {
+                status: entry.status,
+                headers: entry.headers,
+                raw: body_bytes(&entry.body),
+                body: entry.body,
+                attempts: 0,
+                elapsed: Duration::ZERO,
+            });
+        }
+        let started = Instant::now();
+        let (resp, attempts) = self.send(op, path, query, headers, body.as_ref())?;
+        let resp = self.buffer(resp, attempts, Vec::new(), started);
+        if let Some(key) = &cache_key
+            && resp.is_success()
+            && let Err(err) = cache::put(key, resp.status, &resp.headers, &resp.body)
+        {
+            eprintln!("warning: {err:#}");
+        }
+        Ok(resp)
+    }
+
+    /// Like `execute`, but a 2xx top-level JSON array is handed to `item` one element at a
+    /// time as it arrives, so memory stays flat; returns `None` then. Any other response
+    /// is buffered and returned as usual. Bypasses the cache.
+    pub fn stream(
+        &self,
+        op: &Operation,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        body: Option<Body>,
+        item: &mut dyn FnMut(Value) -> Result<()>,
+    ) -> Result<Option<HttpResponse>> {
+        let started = Instant::now();
+        let (resp, attempts) = self.send(op, path, query, headers, body.as_ref())?;
+        if !resp.status().is_success() {
+            return Ok(Some(self.buffer(resp, attempts, Vec::new(), started)));
+        }
+        let mut reader = BufReader::new(resp);
+        let mut prefix = Vec::new();
+        loop {
+            let Some(&byte) = reader.fill_buf().context("read response body")?.first() else {
+                break;
+            };
+            reader.consume(1);
+            prefix.push(byte);
+            if !byte.is_ascii_whitespace() {
+                break;
+            }
+        }
+        if prefix.last() != Some(&b'[') {
+            return Ok(Some(self.buffer(
+                reader.into_inner(),
+                attempts,
+                prefix,
+                started,
+            )));
+        }
+        for_each_array_item(&mut reader, item)?;
+        Ok(None)
+    }
+
+    /// Sends with retries and 429 waits, returning the response to keep and the number of
+    /// attempts it took.
+    fn send(
+        &self,
+        op: &Operation,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        body: Option<&Body>,
+    ) -> Result<(Response, u32)> {
+        let max_attempts = if retryable(&op.method, headers, self.retry_all) {
+            self.retries + 1
+        } else {
+            1
+        };
+
+        let mut attempt = 0;
+        // 429 waits are budgeted separately from transient-failure retries.
+        let mut rate_waits = 0;
+        loop {
+            attempt += 1;
+            let tries = attempt - rate_waits;
+            self.limiter
+                .acquire(&format!("{} {}", op.method, op.path), op.rate_limit);
+            let request = self.build_request(op, path, query, headers, body)?;
+            if self.verbose > 0 {
+                self.request_log_lines(&request)
+                    .iter()
+                    .for_each(|line| eprintln!("{line}"));
+            }
+
+            let resp = match self.client.execute(request).map_err(scrub_url) {
+                Ok(resp) => resp,
+                Err(err) if tries < max_attempts && is_transient_error(&err) => {
+                    let delay = self.backoff(tries);
+                    eprintln!(
+                        "retry {tries}/{}: {err}; waiting {:.1}s",
+                        max_attempts - 1,
+                        delay.as_secs_f64()
+                    );
+                    self.log_retry(op, attempt, None, Some(&err.to_string()), delay);
+                    thread::sleep(delay);
+                    continue;
+                }
+                Err(err) => {
+                    let context = format!("send request (after {attempt} attempt(s))");
+                    return Err(self.describe_send_error(err)).context(context);
+                }
+            };
+
+            let status = resp.status();
+            let headers = resp.headers().clone();
+            if self.verbose > 0 {
+                eprintln!("< HTTP {status}");
+                self.log_headers('<', &headers);
+            }
+            if let Some((remaining, reset_in)) = rate_limit_state(&headers) {
+                self.limiter.observe(remaining, reset_in);
+            }
+            // A 429 means the request wasn't processed, so waiting is safe for any method.
+            if status.as_u16() == 429 && self.rate_limit_wait && rate_waits < self.max_rate_retries
+            {
+                rate_waits += 1;
+                let delay = retry_after(&headers).unwrap_or_else(|| self.backoff(rate_waits));
+                eprintln!(
+                    "rate limited (429): waiting {:.1}s before retry {rate_waits}/{}",
+                    delay.as_secs_f64(),
+                    self.max_rate_retries
+                );
+                self.log_retry(op, attempt, Some(429), None, delay);
+                thread::sleep(delay);
+                continue;
+            }
+            if tries < max_attempts && is_transient_status(status.as_u16()) {
+                let delay = retry_after(&headers).unwrap_or_else(|| self.backoff(tries));
+                eprintln!(
+                    "retry {tries}/{}: http {}; waiting {:.1}s",
+                    max_attempts - 1,
+                    status.as_u16(),
+                    delay.as_secs_f64()
+                );
+                self.log_retry(op, attempt, Some(status.as_u16()), None, delay);
+                thread::sleep(delay);
+                continue;
+            }
+
+            return Ok((resp, attempt));
         }
+    }
 
-        let resp = req.send().context("send request")?;
-        let status = resp.status();
+    /// Reads the rest of `resp` after the already-consumed `prefix` bytes; `started` is
+    /// when the first attempt was sent.
+    fn buffer(
+        &self,
+        resp: Response,
+        attempts: u32,
+        prefix: Vec<u8>,
+        started: Instant,
+    ) -> HttpResponse {
+        let status = resp.status().as_u16();
         let headers = resp.headers().clone();
-        let text = resp.text().unwrap_or_default();
-        let body_value = parse_body_value(&text);
-
-        let output = if raw {
-            let headers_value = headers_to_json(&headers);
-            json!({
-                "status": status.as_u16(),
-                "headers": headers_value,
-                "body": body_value,
-            })
+        let mut bytes = prefix;
+        bytes.extend_from_slice(&resp.bytes().unwrap_or_default());
+        if self.verbose > 1 && !bytes.is_empty() {
+            eprintln!("<\n{}", String::from_utf8_lossy(&bytes));
+        }
+        HttpResponse {
+            status,
+            headers,
+            body: parse_body_value(&bytes),
+            raw: bytes,
+            attempts,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    fn build_request(
+        &self,
+        op: &Operation,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        body: Option<&Body>,
+    ) -> Result<Request> {
+        let tunnelled = self.tunnelled(op);
+        let method = if tunnelled {
+            Method::POST
         } else {
-            body_value
+            op.method.parse().context("invalid http method")?
+        };
+        let mut req = self.client.request(method, self.url(path));
+        req = match self.auth_scheme {
+            AuthScheme::Basic => req.basic_auth(&self.api_key, Some("")),
+            AuthScheme::Bearer => req.bearer_auth(&self.api_key),
+            AuthScheme::None => req,
+        };
+        if tunnelled {
+            req = req.header("X-HTTP-Method-Override", op.method.to_ascii_uppercase());
+        }
+        for (name, value) in self.implicit_headers(headers).chain(headers) {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        req = apply_query(req, query);
+        match body {
+            Some(Body::Json(value)) => req = req.json(value),
+            Some(Body::MergePatch(value)) => {
+                req = req
+                    .header(reqwest::header::CONTENT_TYPE, MERGE_PATCH)
+                    .body(serde_json::to_vec(value)?);
+            }
+            Some(Body::Multipart(parts)) => req = req.multipart(build_form(parts)?),
+            None => {}
+        }
+        req.build().context("build request")
+    }
+
+    /// The request `execute` would send, with credentials masked. Touches no network.
+    pub fn preview(
+        &self,
+        op: &Operation,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        body: Option<&Body>,
+    ) -> Value {
+        let all_headers: Map<String, Value> = self
+            .outgoing_headers(op, headers, body.map(Body::content_type), false)
+            .into_iter()
+            .map(|(name, value)| (name, json!(value)))
+            .collect();
+        json!({
+            "method": self.wire_method(op),
+            "url": self.full_url(path, query, false),
+            "query": query,
+            "headers": all_headers,
+            "body": body.map(Body::to_json),
+        })
+    }
+
+    /// The same request as a runnable `curl` command. Credentials stay masked unless
+    /// `reveal` is set.
+    pub fn curl_command(
+        &self,
+        op: &Operation,
+        path: &str,
+        query: &[(String, String)],
+        headers: &[(String, String)],
+        body: Option<&Body>,
+        reveal: bool,
+    ) -> Result<String> {
+        let mut parts = vec![
+            "curl".to_string(),
+            "-X".to_string(),
+            self.wire_method(op),
+            shell_quote(&self.full_url(path, query, reveal)),
+        ];
+        // curl sets the multipart Content-Type (with its boundary) itself.
+        let content_type = match body {
+            Some(Body::Multipart(_)) | None => None,
+            Some(body) => Some(body.content_type()),
         };
+        for (name, value) in self.outgoing_headers(op, headers, content_type, reveal) {
+            parts.push("-H".to_string());
+            parts.push(shell_quote(&format!("{name}: {value}")));
+        }
+        match body {
+            Some(Body::Json(value) | Body::MergePatch(value)) => {
+                parts.push("--data".to_string());
+                parts.push(shell_quote(&serde_json::to_string(value)?));
+            }
+            Some(Body::Multipart(form)) => {
+                for part in form {
+                    let (flag, spec) = match part {
+                        FormPart::Text { name, value } => {
+                            ("--form-string", format!("{name}={value}"))
+                        }
+                        FormPart::File { name, path } => {
+                            ("-F", format!("{name}=@{}", path.display()))
+                        }
+                    };
+                    parts.push(flag.to_string());
+                    parts.push(shell_quote(&spec));
+                }
+            }
+            None => {}
+        }
+        Ok(parts.join(" "))
+    }
 
-        let rendered = if pretty {
-            serde_json::to_string_pretty(&output)?
+    /// The URL a request would go to, with any base URL credentials stripped; for
+    /// confirmation prompts and messages.
+    pub fn display_url(&self, path: &str, query: &[(String, String)]) -> String {
+        self.full_url(path, query, false)
+    }
+
+    fn wire_method(&self, op: &Operation) -> String {
+        if self.tunnelled(op) {
+            "POST".to_string()
+        } else {
+            op.method.to_ascii_uppercase()
+        }
+    }
+
+    fn full_url(&self, path: &str, query: &[(String, String)], reveal: bool) -> String {
+        let url = self.url(path);
+        let Ok(mut parsed) = Url::parse(&url) else {
+            return url;
+        };
+        if !query.is_empty() {
+            parsed.query_pairs_mut().extend_pairs(query);
+        }
+        if !reveal {
+            redact::strip_credentials(&mut parsed);
+        }
+        parsed.to_string()
+    }
+
+    /// Headers `execute` adds on top of reqwest's defaults; later entries win.
+    fn outgoing_headers(
+        &self,
+        op: &Operation,
+        headers: &[(String, String)],
+        content_type: Option<&str>,
+        reveal: bool,
+    ) -> Vec<(String, String)> {
+        let mut out: Vec<(String, String)> = Vec::new();
+        let authorization = match self.auth_scheme {
+            AuthScheme::Basic => {
+                let credentials = format!("{}:", self.api_key);
+                let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+                Some(format!("Basic {encoded}"))
+            }
+            AuthScheme::Bearer => Some(format!("Bearer {}", self.api_key)),
+            AuthScheme::None => None,
+        };
+        if let Some(value) = authorization {
+            let value = if reveal {
+                value
+            } else {
+                redact::redact_secret(&value)
+            };
+            out.push(("Authorization".to_string(), value));
+        }
+        if self.tunnelled(op) {
+            out.push((
+                "X-HTTP-Method-Override".to_string(),
+                op.method.to_ascii_uppercase(),
+            ));
+        }
+        if let Some(content_type) = content_type {
+            out.push(("Content-Type".to_string(), content_type.to_string()));
+        }
+        out.extend(self.default_headers.iter().cloned());
+        for (name, value) in headers {
+            let value = if reveal {
+                value.clone()
+            } else {
+                self.redact.mask(name, value).into_owned()
+            };
+            out.retain(|(existing, _)| !existing.eq_ignore_ascii_case(name));
+            out.push((name.clone(), value));
+        }
+        out
+    }
+
+    /// `-v` lines for an outgoing request; `-vv` adds the body.
+    fn request_log_lines(&self, request: &Request) -> Vec<String> {
+        let mut url = request.url().clone();
+        redact::strip_credentials(&mut url);
+        let mut lines = vec![format!("> {} {url}", request.method())];
+        lines.extend(self.header_lines('>', request.headers()));
+        if self.verbose > 1
+            && let Some(bytes) = request.body().and_then(|body| body.as_bytes())
+        {
+            lines.push(format!(">\n{}", String::from_utf8_lossy(bytes)));
+        }
+        lines
+    }
+
+    fn log_headers(&self, direction: char, headers: &HeaderMap) {
+        self.header_lines(direction, headers)
+            .iter()
+            .for_each(|line| eprintln!("{line}"));
+    }
+
+    fn header_lines(&self, direction: char, headers: &HeaderMap) -> Vec<String> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or("<binary>");
+                format!(
+                    "{direction} {name}: {}",
+                    self.redact.mask(name.as_str(), value)
+                )
+            })
+            .collect()
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Default headers not overridden by an explicit `--header` of the same name.
+    fn implicit_headers<'a>(
+        &'a self,
+        headers: &[(String, String)],
+    ) -> impl Iterator<Item = &'a (String, String)> {
+        self.default_headers
+            .iter()
+            .filter(move |(name, _)| header_value(headers, name).is_none())
+    }
+
+    fn tunnelled(&self, op: &Operation) -> bool {
+        self.method_override
+            .iter()
+            .any(|verb| verb.eq_ignore_ascii_case(&op.method))
+    }
+
+    /// Names the limit that was hit for timeouts, keeping the `reqwest::Error` in the
+    /// chain so the exit code can still be derived from it.
+    fn describe_send_error(&self, err: reqwest::Error) -> anyhow::Error {
+        if !err.is_timeout() {
+            return err.into();
+        }
+        let (kind, limit) = if err.is_connect() {
+            ("connection", self.connect_timeout.or(self.timeout))
         } else {
-            serde_json::to_string(&output)?
+            ("request", self.timeout)
+        };
+        let message = match limit {
+            Some(limit) => format!("{kind} timed out after {} seconds", limit.as_secs_f64()),
+            None => format!("{kind} timed out"),
+        };
+        anyhow::Error::new(err).context(message)
+    }
+
+    /// One `--retry-log` line for a failed `attempt` that is about to be retried after
+    /// `delay`. Write failures only warn.
+    fn log_retry(
+        &self,
+        op: &Operation,
+        attempt: u32,
+        status: Option<u16>,
+        error: Option<&str>,
+        delay: Duration,
+    ) {
+        let Some(file) = &self.retry_log else {
+            return;
         };
+        let entry = json!({
+            "ts": clock::now_rfc3339(),
+            "method": op.method.to_ascii_uppercase(),
+            "path": op.path,
+            "attempt": attempt,
+            "status": status,
+            "error": error,
+            "delay_ms": delay.as_millis() as u64,
+        });
+        let mut file = file.lock().unwrap_or_else(|err| err.into_inner());
+        if let Err(err) = writeln!(file, "{entry}") {
+            eprintln!("warning: --retry-log not written: {err}");
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.retry_backoff.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
+
+/// Single-quotes `text` for POSIX shells.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Follows up to `max` redirects, logging each under `-v`.
+fn redirect_policy(max: usize, verbose: bool) -> Policy {
+    if max == 0 {
+        return Policy::none();
+    }
+    // reqwest itself drops `Authorization` (and cookies) when a redirect changes scheme,
+    // host or port, so the key never reaches another origin.
+    Policy::custom(move |attempt| {
+        // `previous` starts with the original URL.
+        if attempt.previous().len() > max {
+            return attempt.error(format!("stopped after {max} redirects (--max-redirects)"));
+        }
+        if verbose {
+            let cross_origin = attempt
+                .previous()
+                .last()
+                .is_some_and(|previous| previous.origin() != attempt.url().origin());
+            let mut url = attempt.url().clone();
+            redact::strip_credentials(&mut url);
+            eprintln!(
+                "< redirect {} to {url}{}",
+                attempt.status().as_u16(),
+                if cross_origin {
+                    " (other origin: authorization dropped)"
+                } else {
+                    ""
+                }
+            );
+        }
+        attempt.follow()
+    })
+}
 
-        Ok((rendered, status.is_success(), status.as_u16()))
+/// reqwest errors print their URL; keep any `user:password@` out of messages.
+fn scrub_url(mut err: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = err.url_mut() {
+        redact::strip_credentials(url);
     }
+    err
+}
+
+pub const IDEMPOTENCY_HEADER: &str = "Idempotency-key";
+pub const FOR_USER_ID_HEADER: &str = "for-user-id";
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Only reads retry by default: a failed write may still have been applied. `retry_all`
+/// adds PUT/PATCH/DELETE, and POST only when it carries an idempotency key, since a
+/// blind retry of a create could charge twice.
+fn retryable(method: &str, headers: &[(String, String)], retry_all: bool) -> bool {
+    match method.to_ascii_uppercase().as_str() {
+        "GET" | "HEAD" => true,
+        "POST" => {
+            retry_all
+                && headers
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_HEADER))
+        }
+        _ => retry_all,
+    }
+}
+
+fn is_transient_status(status: u16) -> bool {
+    matches!(status, 502..=504)
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || err.is_request()
+}
+
+/// Parses a `Retry-After` header given in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get("retry-after")?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = clock::parse_http_date(value)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Some(Duration::from_secs(at.saturating_sub(now)))
+}
+
+fn rate_limit_state(headers: &HeaderMap) -> Option<(u64, Duration)> {
+    let number = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    let remaining = number("x-ratelimit-remaining")?;
+    let reset = number("x-ratelimit-reset").unwrap_or(0);
+    // Large values are epoch seconds; small ones are seconds until the window resets.
+    let reset_in = if reset > 1_000_000_000 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Duration::from_secs(reset.saturating_sub(now))
+    } else {
+        Duration::from_secs(reset)
+    };
+    Some((remaining, reset_in))
 }
 
 fn apply_query(req: RequestBuilder, query: &[(String, String)]) -> RequestBuilder {
@@ -73,18 +976,319 @@ fn apply_query(req: RequestBuilder, query: &[(String, String)]) -> RequestBuilde
     req.query(&query)
 }
 
-fn parse_body_value(text: &str) -> Value {
+/// JSON when it parses, otherwise the text as-is; bytes that aren't UTF-8 are kept
+/// intact as base64 rather than lossily decoded.
+fn parse_body_value(bytes: &[u8]) -> Value {
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return json!({
+            "encoding": "base64",
+            "data": base64::engine::general_purpose::STANDARD.encode(bytes),
+        });
+    };
     if text.trim().is_empty() {
         return Value::Null;
     }
     serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
 }
 
-fn headers_to_json(headers: &HeaderMap) -> Value {
-    let mut map = Map::new();
-    for (key, value) in headers.iter() {
-        let val = value.to_str().unwrap_or("").to_string();
-        map.insert(key.to_string(), Value::String(val));
+/// Splits the elements of a JSON array whose `[` was already consumed, parsing each as
+/// soon as its closing `,` or `]` arrives.
+fn for_each_array_item(
+    reader: &mut impl Read,
+    item: &mut dyn FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    let mut element = Vec::new();
+    let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+    for byte in reader.bytes() {
+        let byte = byte.context("read response body")?;
+        if in_string {
+            element.push(byte);
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match byte {
+            b',' | b']' if depth == 0 => {
+                if !element.iter().all(u8::is_ascii_whitespace) {
+                    item(serde_json::from_slice(&element).context("invalid array element")?)?;
+                }
+                element.clear();
+                if byte == b']' {
+                    return Ok(());
+                }
+            }
+            b'"' => {
+                in_string = true;
+                element.push(byte);
+            }
+            b'[' | b'{' => {
+                depth += 1;
+                element.push(byte);
+            }
+            b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                element.push(byte);
+            }
+            _ => element.push(byte),
+        }
+    }
+    Err(anyhow!("response ended inside a JSON array"))
+}
+
+/// Inverse of `parse_body_value`, for bodies restored from the cache.
+fn body_bytes(body: &Value) -> Vec<u8> {
+    if body.get("encoding").and_then(Value::as_str) == Some("base64")
+        && let Some(data) = body.get("data").and_then(Value::as_str)
+        && let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data)
+    {
+        return bytes;
+    }
+    match body {
+        Value::Null => Vec::new(),
+        Value::String(text) => text.clone().into_bytes(),
+        other => other.to_string().into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, TestServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn op(method: &str) -> Operation {
+        serde_json::from_value(json!({
+            "name": "op", "method": method, "path": "/things", "params": [], "has_body": false,
+        }))
+        .unwrap()
+    }
+
+    /// Answers 503 once, then 200.
+    fn flaky_server() -> TestServer {
+        let calls = AtomicUsize::new(0);
+        TestServer::start(move |_| match calls.fetch_add(1, Ordering::SeqCst) {
+            0 => Reply::json(503, r#"{"error_code":"SERVER_ERROR"}"#),
+            _ => Reply::json(200, "{}"),
+        })
+    }
+
+    fn send(
+        server: &TestServer,
+        method: &str,
+        retry_all: bool,
+        headers: &[(String, String)],
+    ) -> u16 {
+        let opts = ClientOptions {
+            retries: 2,
+            retry_all,
+            ..ClientOptions::default()
+        };
+        let client = HttpClient::new(server.base_url.clone(), "key".into(), &opts).unwrap();
+        client
+            .execute(&op(method), "/things", &[], headers, None)
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn only_reads_retry_by_default() {
+        assert!(retryable("GET", &[], false));
+        assert!(retryable("head", &[], false));
+        for method in ["PUT", "PATCH", "DELETE", "POST"] {
+            assert!(!retryable(method, &[], false), "{method}");
+        }
+    }
+
+    #[test]
+    fn retry_all_still_needs_an_idempotency_key_for_post() {
+        let keyed = [(IDEMPOTENCY_HEADER.to_string(), "key-1".to_string())];
+        for method in ["PUT", "PATCH", "DELETE"] {
+            assert!(retryable(method, &[], true), "{method}");
+        }
+        assert!(!retryable("POST", &[], true));
+        assert!(retryable("POST", &keyed, true));
+        assert!(!retryable("POST", &keyed, false));
+    }
+
+    #[test]
+    fn a_get_is_retried_after_a_503() {
+        let server = flaky_server();
+        assert_eq!(send(&server, "GET", false, &[]), 200);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn a_write_is_not_retried_by_default() {
+        let server = flaky_server();
+        assert_eq!(send(&server, "DELETE", false, &[]), 503);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn retry_all_retries_writes() {
+        let server = flaky_server();
+        assert_eq!(send(&server, "DELETE", true, &[]), 200);
+        assert_eq!(server.requests().len(), 2);
+
+        let server = flaky_server();
+        let keyed = [(IDEMPOTENCY_HEADER.to_string(), "key-1".to_string())];
+        assert_eq!(send(&server, "POST", true, &keyed), 200);
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn gzipped_json_is_decompressed_and_parsed() {
+        let server = TestServer::start(|_| Reply {
+            status: 200,
+            headers: vec![
+                ("content-type".into(), "application/json".into()),
+                ("content-encoding".into(), "gzip".into()),
+            ],
+            body: include_bytes!("../tests/fixtures/invoice.json.gz").to_vec(),
+        });
+        let client = HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &ClientOptions::default(),
+        )
+        .unwrap();
+        let resp = client
+            .execute(&op("GET"), "/v2/invoices/1", &[], &[], None)
+            .unwrap();
+
+        let fixture = include_bytes!("../tests/fixtures/invoice.json");
+        assert_eq!(resp.raw, fixture);
+        assert_eq!(resp.body, serde_json::from_slice::<Value>(fixture).unwrap());
+        assert_eq!(resp.body["status"], "PENDING");
+        assert_eq!(resp.non_json_content_type(), None);
+        let accept = server.requests()[0]
+            .header("accept-encoding")
+            .unwrap()
+            .to_string();
+        assert!(accept.contains("gzip"), "{accept}");
+    }
+
+    #[test]
+    fn binary_bodies_are_kept_intact() {
+        let bytes = vec![0x25, 0x50, 0x44, 0x46, 0xff, 0x00, 0xfe];
+        let sent = bytes.clone();
+        let server = TestServer::start(move |_| Reply {
+            status: 200,
+            headers: vec![("content-type".into(), "application/pdf".into())],
+            body: sent.clone(),
+        });
+        let client = HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &ClientOptions::default(),
+        )
+        .unwrap();
+        let resp = client
+            .execute(&op("GET"), "/reports/1", &[], &[], None)
+            .unwrap();
+        assert_eq!(resp.raw, bytes);
+        assert_eq!(resp.body["encoding"], "base64");
+        assert_eq!(resp.non_json_content_type(), Some("application/pdf"));
+    }
+
+    #[test]
+    fn the_key_never_appears_in_verbose_or_dry_run_output() {
+        const KEY: &str = "xnd_development_s3cr3tKEYvalue";
+        let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{KEY}:"));
+        let opts = ClientOptions {
+            verbose: 2,
+            // Naming other headers must not unmask the built-in ones.
+            redact: RedactSet::parse("x-custom"),
+            ..ClientOptions::default()
+        };
+        let client = HttpClient::new("https://api.xendit.co".into(), KEY.into(), &opts).unwrap();
+        let op = op("POST");
+        let headers = [("Proxy-Authorization".to_string(), format!("Bearer {KEY}"))];
+        let body = Body::Json(json!({"amount": 10000}));
+
+        let request = client
+            .build_request(&op, "/things", &[], &headers, Some(&body))
+            .unwrap();
+        let mut output = client.request_log_lines(&request);
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert("set-cookie", format!("session={KEY}").parse().unwrap());
+        output.extend(client.header_lines('<', &response_headers));
+        output.push(
+            client
+                .preview(&op, "/things", &[], &headers, Some(&body))
+                .to_string(),
+        );
+        output.push(
+            client
+                .curl_command(&op, "/things", &[], &headers, Some(&body), false)
+                .unwrap(),
+        );
+
+        let output = output.join("\n");
+        assert!(output.contains("Basic ****"), "{output}");
+        assert!(!output.contains("s3cr3tKEYvalue"), "{output}");
+        assert!(!output.contains(&encoded), "{output}");
+    }
+
+    #[test]
+    fn multipart_bodies_carry_text_and_file_parts() {
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+        let client = HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &ClientOptions::default(),
+        )
+        .unwrap();
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/document.pdf");
+        let body = Body::Multipart(vec![
+            FormPart::Text {
+                name: "document_type".into(),
+                value: "KTP".into(),
+            },
+            FormPart::File {
+                name: "file".into(),
+                path: fixture.clone(),
+            },
+        ]);
+        client
+            .execute(&op("POST"), "/documents", &[], &[], Some(body))
+            .unwrap();
+
+        let request = &server.requests()[0];
+        let content_type = request.header("content-type").unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap_or_else(|| panic!("{content_type}"));
+        let raw = String::from_utf8_lossy(&request.body).into_owned();
+        let parts: Vec<&str> = raw
+            .split(&format!("--{boundary}"))
+            .map(|part| part.trim_start_matches("\r\n"))
+            .filter(|part| !part.is_empty() && !part.starts_with("--"))
+            .collect();
+        assert_eq!(parts.len(), 2, "{raw}");
+
+        let (head, value) = parts[0].split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            head.to_ascii_lowercase(),
+            "content-disposition: form-data; name=\"document_type\""
+        );
+        assert_eq!(value, "KTP\r\n");
+
+        let (head, contents) = parts[1].split_once("\r\n\r\n").unwrap();
+        let head = head.to_ascii_lowercase();
+        assert!(
+            head.contains("name=\"file\"; filename=\"document.pdf\""),
+            "{head}"
+        );
+        assert!(head.contains("content-type: application/pdf"), "{head}");
+        assert_eq!(
+            contents.as_bytes(),
+            [fs::read(&fixture).unwrap(), b"\r\n".to_vec()].concat()
+        );
     }
-    Value::Object(map)
 }
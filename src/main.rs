@@ -1,113 +1,2193 @@
+mod assertions;
+mod batch;
+mod cache;
+mod capture;
+mod clock;
 mod command_tree;
+mod config;
+mod diff;
+mod docs;
+mod editor;
+mod errors;
+mod history;
 mod http;
+mod openapi;
+mod pagination;
+mod param_file;
+mod poll;
+mod progress;
+mod prompt;
+mod rate_limit;
+mod redact;
+mod render;
+mod schema_diff;
+mod select;
+mod suggest;
+mod table;
+#[cfg(test)]
+mod test_server;
+mod webhook;
 
 use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use clap::parser::ValueSource;
 use clap::{Arg, ArgAction, Command};
-use command_tree::{CommandTree, Operation, ParamDef};
+use command_tree::{CommandTree, Operation, ParamDef, Resource};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
 use serde_json::Value;
-use std::{env, fs, io::Write, path::Path};
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
 
 fn main() {
     if let Err(err) = run() {
-        eprintln!("error: {err}");
-        std::process::exit(1);
+        // Read from argv: the failure may have happened before (or inside) clap parsing.
+        if early_flag_value("--error-format").as_deref() == Some("json") {
+            eprintln!("{}", error_json(&err));
+        } else {
+            eprintln!("error: {err}");
+        }
+        std::process::exit(exit_code(&err));
+    }
+}
+
+/// `--error-format json`: the HTTP failure's fields, or just the message chain.
+fn error_json(err: &anyhow::Error) -> Value {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<http::StatusError>())
+        .map(http::StatusError::to_json)
+        .unwrap_or_else(|| serde_json::json!({"error": {"message": format!("{err:#}")}}))
+}
+
+/// curl-style exit codes so scripts can branch on the failure class:
+/// 22 for HTTP 4xx, 23 for 5xx, 28 for timeouts, 7 for connection failures, 1 otherwise.
+fn exit_code(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(status) = cause.downcast_ref::<http::StatusError>() {
+            return match status.status {
+                400..=499 => 22,
+                500..=599 => 23,
+                _ => 1,
+            };
+        }
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            if err.is_timeout() {
+                return 28;
+            }
+            if err.is_connect() {
+                return 7;
+            }
+        }
     }
+    1
 }
 
 fn run() -> Result<()> {
-    let tree = command_tree::load_command_tree();
-    let cli = build_cli(&tree);
-    let matches = cli.get_matches();
+    // The tree shapes the CLI itself, so its source must be known before clap parses.
+    let mut tree = match early_flag_value("--command-tree-dir") {
+        Some(dir) => command_tree::load_command_tree_dir(Path::new(&dir))?,
+        None => match early_flag_value("--schema").or_else(|| env::var("XENDIT_SCHEMA").ok()) {
+            Some(path) => command_tree::load_command_tree_file(Path::new(&path))?,
+            None => command_tree::load_command_tree()?,
+        },
+    };
+    command_tree::validate_command_tree(&tree)?;
+    // Checked before loading, which would stop at the first problem.
+    if env::args().skip(1).any(|arg| arg == "--config-check") {
+        return handle_config_check();
+    }
+    let selected = early_flag_value("--profile").or_else(|| env::var("XENDIT_PROFILE").ok());
+    let environment = early_flag_value("--env")
+        .or_else(|| env::var("XENDIT_ENV").ok().filter(|name| !name.is_empty()));
+    let policy = early_flag_value("--policy");
+    let active = config::load(
+        selected.as_deref(),
+        environment.as_deref(),
+        policy.as_deref(),
+    )?;
+    command_tree::apply_param_defaults(&mut tree, &active.profile.param_defaults);
+    let cli = build_cli(&tree).args_override_self(true);
+    let user_args = expand_alias(&cli, &active.aliases, env::args().skip(1).collect())?;
+    // Profile flags go first so anything on the real command line overrides them.
+    let args: Vec<String> = env::args()
+        .take(1)
+        .chain(active.profile.flags.iter().cloned())
+        .chain(user_args)
+        .collect();
+    let matches = cli.get_matches_from(args);
 
     if let Some(matches) = matches.subcommand_matches("list") {
         return handle_list(&tree, matches);
     }
+    if let Some(matches) = matches.subcommand_matches("search") {
+        return handle_search(&tree, matches);
+    }
     if let Some(matches) = matches.subcommand_matches("describe") {
         return handle_describe(&tree, matches);
     }
     if let Some(matches) = matches.subcommand_matches("tree") {
         return handle_tree(&tree, matches);
     }
+    if let Some(matches) = matches.subcommand_matches("stats") {
+        return handle_stats(&tree, matches);
+    }
+    if let Some(batch_matches) = matches.subcommand_matches("batch") {
+        return handle_batch(&tree, &active.profile, batch_matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("verify-webhook") {
+        return handle_verify_webhook(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("validate-schema") {
+        return handle_validate_schema(&tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("docs") {
+        return handle_docs(&tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("export-openapi") {
+        return handle_export_openapi(&tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("diff-schema") {
+        return handle_diff_schema(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        return handle_generate(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("completions") {
+        return handle_completions(&tree, matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("cache") {
+        return handle_cache(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("history") {
+        return handle_history(matches);
+    }
+    if let Some(config_matches) = matches.subcommand_matches("config") {
+        return handle_config(&tree, &active, config_matches);
+    }
+    if let Some(watch_matches) = matches.subcommand_matches("watch") {
+        return handle_watch(&tree, &active.profile, &matches, watch_matches);
+    }
 
-    let base_url = matches
-        .get_one::<String>("base_url")
-        .cloned()
-        .or_else(|| env::var("XENDIT_API_URL").ok())
-        .unwrap_or_else(|| tree.base_url.clone());
-
-    let api_key = matches
-        .get_one::<String>("api_key")
-        .cloned()
-        .or_else(|| env::var("XENDIT_API_KEY").ok())
-        .context("XENDIT_API_KEY missing")?;
+    run_operation(&tree, &active.profile, &matches, &matches, true)
+}
 
-    let pretty = matches.get_flag("pretty");
-    let raw = matches.get_flag("raw");
+/// Runs the `<resource> <op>` found under `commands`; global flags are read from `matches`.
+/// `banner` is false when the caller has already shown the production banner.
+fn run_operation(
+    tree: &CommandTree,
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+    commands: &clap::ArgMatches,
+    banner: bool,
+) -> Result<()> {
+    let render_opts = render::RenderOptions {
+        format: render::OutputFormat::from_name(
+            matches
+                .get_one::<String>("output")
+                .map(String::as_str)
+                .unwrap_or("json"),
+        )?,
+        raw: matches.get_flag("raw"),
+        pretty: matches.get_flag("pretty"),
+        limit_output: matches.get_one::<usize>("limit_output").copied(),
+        redact: redact_set(matches),
+        lossy_numbers: matches.get_flag("lossy_numbers"),
+        headers_only: matches.get_flag("headers_only"),
+        canonical: matches.get_flag("canonical"),
+        columns: matches
+            .get_many::<String>("columns")
+            .into_iter()
+            .flatten()
+            .map(|column| column.trim().to_string())
+            .filter(|column| !column.is_empty())
+            .collect(),
+        select: matches
+            .get_one::<String>("select")
+            .map(String::as_str)
+            .map(select::Selector::parse)
+            .transpose()?,
+        number_grouping: matches.get_flag("pretty_numbers").then(|| {
+            table::group_separator(
+                matches
+                    .get_one::<String>("locale")
+                    .map(String::as_str)
+                    .unwrap_or("en"),
+            )
+        }),
+        timing: matches.get_flag("timing"),
+        // Escape sequences would end up in files and in what --transform reads.
+        hyperlinks: matches.get_flag("hyperlinks")
+            && std::io::stdout().is_terminal()
+            && !matches.contains_id("output_file")
+            && !matches.contains_id("transform"),
+    };
 
-    let (res_name, res_matches) = matches
+    let (res_name, res_matches) = commands
         .subcommand()
         .ok_or_else(|| anyhow!("resource required"))?;
     let (op_name, op_matches) = res_matches
         .subcommand()
         .ok_or_else(|| anyhow!("operation required"))?;
 
-    let op = find_op(&tree, res_name, op_name)
-        .ok_or_else(|| anyhow!("unknown command {res_name} {op_name}"))?;
+    let op = find_op(tree, res_name, op_name)
+        .ok_or_else(|| unknown_command(tree, res_name, op_name))?;
+    if !matches.get_flag("no_deprecation_warnings") {
+        warn_deprecated(res_name, op);
+    }
+
+    let example = match op_matches.get_one::<usize>("example_run") {
+        Some(&index) => Some(select_example(op, index)?),
+        None => None,
+    };
+
+    let params = match matches.get_one::<String>("param_file") {
+        Some(path) => Some(param_file::ParamFile::load(
+            path,
+            matches
+                .get_one::<String>("param_file_format")
+                .map(String::as_str)
+                .unwrap_or("auto"),
+            op,
+        )?),
+        None => None,
+    };
 
-    let (path, query) = build_request_parts(op, op_matches)?;
-    let body = if op.has_body {
+    let mut captures = capture::Captures::default();
+    let interactive = matches.get_flag("interactive")
+        && !matches.get_flag("no_interactive")
+        && prompt::available();
+    let (path, mut query, param_headers) = build_request_parts(
+        op,
+        Some(op_matches),
+        &captures,
+        params.as_ref(),
+        example,
+        interactive,
+    )?;
+    if let Some(since_id) = matches.get_one::<String>("since_id") {
+        apply_since_id(op, matches, &mut query, since_id)?;
+    }
+    let json_body = op.has_body && !op.is_multipart();
+    let mut body = if json_body {
         parse_body_arg(op_matches)?
+            .or_else(|| example.and_then(|example| example.get("body").cloned()))
     } else {
         None
     };
+    if body.is_none() && json_body && interactive {
+        // No body schema in the tree; the first example is the best available skeleton.
+        let skeleton = match op.examples.first().and_then(|example| example.get("body")) {
+            Some(body) => serde_json::to_string_pretty(body)?,
+            None => "{\n}\n".to_string(),
+        };
+        body = Some(editor::edit_json(&skeleton)?);
+    }
+    if let Some(body) = body.as_mut() {
+        captures.expand_value(body)?;
+    }
+    if matches.get_flag("dump_parsed_body")
+        && let Some(body) = &body
+    {
+        eprintln!("{}", serde_json::to_string_pretty(body)?);
+    }
+
+    // Header params first, so a --header with the same name is sent after them.
+    let mut headers = param_headers;
+    for raw in matches.get_many::<String>("header").into_iter().flatten() {
+        headers.push(parse_header_arg(raw)?);
+    }
+    if let Some(key) = idempotency_key(op, matches) {
+        headers.push((http::IDEMPOTENCY_HEADER.to_string(), key));
+    }
+
+    let body = if op.is_multipart() {
+        Some(http::Body::Multipart(parse_form_args(op_matches, &captures)?))
+    } else {
+        // Only PATCH operations define --merge.
+        let merge = op_matches.try_contains_id("merge").unwrap_or(false);
+        body.map(|body| json_body(op, body, merge))
+    };
 
-    let client = http::HttpClient::new(base_url, api_key)?;
-    let (output, ok, status) = client.execute(&op.method, &path, &query, body, raw, pretty)?;
+    // Parsed before sending so a typo fails without a request.
+    let conditions = matches
+        .get_many::<String>("assert")
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .map(select::Condition::parse)
+        .collect::<Result<Vec<_>>>()?;
 
-    write_stdout_line(&output)?;
-    if !ok {
-        return Err(anyhow!("http {}", status));
+    let client = build_client(tree, profile, matches, banner)?;
+    let unsafe_curl = matches.get_flag("unsafe_print_curl");
+    if matches.get_flag("print_curl") || unsafe_curl {
+        eprintln!(
+            "{}",
+            client.curl_command(op, &path, &query, &headers, body.as_ref(), unsafe_curl)?
+        );
+    }
+    if matches.get_flag("dry_run") {
+        let preview = client.preview(op, &path, &query, &headers, body.as_ref());
+        write_stdout_line(&serde_json::to_string_pretty(&preview)?)?;
+        return Ok(());
+    }
+    let destructive = ["POST", "DELETE"]
+        .iter()
+        .any(|method| op.method.eq_ignore_ascii_case(method));
+    confirm_operation(
+        profile,
+        matches,
+        destructive,
+        needs_confirmation(op, matches),
+        &format!(
+            "{} {res_name} {} ({})",
+            op.method.to_ascii_uppercase(),
+            op.name,
+            client.display_url(&path, &query)
+        ),
+    )?;
+    let quiet = matches.get_flag("quiet");
+    let mut history = history::Recorder::start(
+        res_name,
+        op,
+        profile.environment.as_ref().map(|env| env.name.as_str()),
+        !matches.get_flag("no_history") && profile.history != Some(false),
+    );
+    let resp = {
+        let _spinner = progress::Spinner::start(
+            format!("{} {}", op.method, path),
+            Duration::from_millis(300),
+            // A spinner would interleave with streamed lines and poll progress on the terminal.
+            !matches.get_flag("no_progress")
+                && !matches.get_flag("stream")
+                && !matches.contains_id("poll_until"),
+        );
+        if let Some(poll_opts) = poll_options(op, matches)? {
+            poll::poll_until(&client, op, &path, &query, &headers, &poll_opts)?
+        } else if matches.get_flag("paginate") {
+            if op.has_body {
+                return Err(anyhow!("--paginate only applies to operations without a body"));
+            }
+            let page_opts = pagination::PageOptions {
+                cursor_param: matches
+                    .get_one::<String>("cursor_param")
+                    .cloned()
+                    .unwrap_or_else(|| "after_id".to_string()),
+                max_pages: matches.get_one::<usize>("max_pages").copied().unwrap_or(100),
+            };
+            pagination::fetch_all(&client, op, &path, &query, &headers, &page_opts)?
+        } else if matches.get_flag("stream") {
+            let mut print_item = |item: Value| -> Result<()> {
+                if quiet {
+                    return Ok(());
+                }
+                if let Some(line) = render::render_stream_item(&item, &render_opts)? {
+                    write_stdout_line(&line)?;
+                }
+                Ok(())
+            };
+            match client.stream(op, &path, &query, &headers, body, &mut print_item)? {
+                Some(resp) => resp,
+                None => {
+                    history.responded(None);
+                    return Ok(());
+                }
+            }
+        } else {
+            client.execute(op, &path, &query, &headers, body)?
+        }
+    };
+    history.responded(Some(resp.status));
+    // With --raw the same figures go into the envelope's `meta` instead.
+    if render_opts.timing && !render_opts.raw {
+        eprintln!("{}", timing_line(&resp));
+    }
+    let output_file = matches.get_one::<String>("output_file");
+    // Binary bodies go to disk byte-for-byte instead of through the JSON renderer.
+    let download = resp.is_success()
+        && (matches.get_flag("download")
+            || (output_file.is_some() && resp.non_json_content_type().is_some()));
+    if download {
+        let target = match output_file {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(
+                resp.attachment_filename()
+                    .or_else(|| path_file_name(&path))
+                    .unwrap_or_else(|| "download".to_string()),
+            ),
+        };
+        write_file_bytes(&target, &resp.raw)?;
+        eprintln!(
+            "saved {} ({} bytes, {})",
+            target.display(),
+            resp.raw.len(),
+            resp.headers
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or("unknown content type")
+        );
+    }
+    let changes = match matches.get_one::<String>("diff_against") {
+        Some(baseline) if resp.is_success() => Some(diff::against_baseline(
+            baseline,
+            &resp.body,
+            matches.get_flag("update_baseline"),
+        )?),
+        _ => None,
+    };
+    let output = match &changes {
+        // --quiet only silences stdout; --output-file is still written.
+        _ if quiet && output_file.is_none() => None,
+        Some(changes) => Some(render::render_output(
+            &diff::changes_to_json(changes),
+            &render_opts,
+        )?),
+        None if download => None,
+        None => render::render_response(&resp, &render_opts)?,
+    };
+    if let Some(mut output) = output {
+        if let Some(command) = matches.get_one::<String>("transform") {
+            output = render::transform_output(command, &output)?;
+        }
+        match output_file {
+            Some(path) => write_output_file(Path::new(path), &output)?,
+            None => write_stdout_line(&output)?,
+        }
+    }
+    let expected_status: Vec<String> = matches
+        .get_many::<String>("assert_status")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if !expected_status.is_empty() {
+        assertions::check_status(&expected_status, resp.status)?;
+    } else if !resp.is_success() {
+        if matches.get_flag("explain_error")
+            && let Some(code) = errors::error_code(&resp.body)
+        {
+            match errors::explain(code) {
+                Some((explanation, fix)) => eprintln!("{code}: {explanation}\nfix: {fix}"),
+                None => eprintln!("{code}: no explanation available"),
+            }
+        }
+        return Err(http::StatusError::from_response(&resp).into());
+    }
+    if let Some(schema_path) = matches.get_one::<String>("assert_schema") {
+        assertions::check_schema(schema_path, &resp.body)?;
+    }
+    if !conditions.is_empty() {
+        assertions::check_conditions(&conditions, &resp.body)?;
+    }
+    if let Some(changes) = &changes
+        && !changes.is_empty()
+        && matches.get_flag("fail_on_change")
+    {
+        return Err(anyhow!("{} field(s) changed since baseline", changes.len()));
+    }
+    let capture_specs: Vec<String> = matches
+        .get_many::<String>("capture")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if !capture_specs.is_empty() {
+        captures.record(&capture_specs, &resp.body)?;
+        captures.save()?;
     }
     Ok(())
 }
 
-fn build_cli(tree: &CommandTree) -> Command {
-    let mut cmd = Command::new("xendit")
-        .about("Xendit CLI (auto-generated)")
-        .subcommand_required(true)
-        .arg_required_else_help(true)
-        .arg(
-            Arg::new("pretty")
-                .long("pretty")
-                .global(true)
-                .action(ArgAction::SetTrue)
-                .help("Pretty-print JSON output"),
-        )
-        .arg(
-            Arg::new("raw")
-                .long("raw")
-                .global(true)
-                .action(ArgAction::SetTrue)
-                .help("Include status and headers"),
-        )
-        .arg(
-            Arg::new("base_url")
-                .long("base-url")
-                .global(true)
-                .value_name("URL")
-                .help("Override base API URL"),
-        )
-        .arg(
-            Arg::new("api_key")
-                .long("api-key")
-                .global(true)
-                .value_name("KEY")
-                .help("Override XENDIT_API_KEY"),
+/// Stderr only, so neither stdout nor the exit code changes.
+fn warn_deprecated(resource: &str, op: &Operation) {
+    if let Some(note) = op.deprecation_note() {
+        eprintln!("warning: {resource} {} is {note}", op.name);
+    }
+}
+
+/// `timing: 200 in 143ms, 5120 bytes`, noting retries and cache hits.
+fn timing_line(resp: &http::HttpResponse) -> String {
+    let mut line = format!(
+        "timing: {} in {}ms, {} bytes",
+        resp.status,
+        resp.elapsed.as_millis(),
+        resp.raw.len()
+    );
+    match resp.attempts {
+        0 => line.push_str(" (cached)"),
+        1 => {}
+        attempts => line.push_str(&format!(" ({attempts} attempts)")),
+    }
+    line
+}
+
+/// Red on a terminal (unless `NO_COLOR` is set), plain otherwise so logs stay readable.
+fn production_banner(base_url: &str) {
+    let text = format!("PRODUCTION: requests go to {base_url}");
+    if std::io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none() {
+        eprintln!("\x1b[1;41;97m {text} \x1b[0m");
+    } else {
+        eprintln!("{text}");
+    }
+}
+
+/// Resolves credentials and network flags into a client; shared by single runs and `batch`.
+fn build_client(
+    tree: &CommandTree,
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+    banner: bool,
+) -> Result<http::HttpClient> {
+    let (base_url, _) = resolve_base_url(tree, profile, matches);
+    let policy = profile.policy.as_ref();
+    if banner && profile.is_production() && !matches.get_flag("no_banner") {
+        production_banner(&base_url);
+    }
+
+    let api_version = resolve_setting(
+        matches,
+        "api_version",
+        "XENDIT_API_VERSION",
+        None,
+        &profile.api_version,
+    )
+    .filter(|(value, _)| !value.trim().is_empty());
+    if matches.get_count("verbose") > 0
+        && let Some((value, source)) = &api_version
+    {
+        eprintln!("api version: {} {value} ({source})", tree.version_header());
+    }
+
+    let auth_scheme = http::AuthScheme::from_name(
+        matches
+            .get_one::<String>("auth_scheme")
+            .map(String::as_str)
+            .unwrap_or("basic"),
+    )?;
+    let api_key = resolve_api_key(matches, profile)?
+        .map(|(value, _)| value)
+        // A dry run or unauthenticated request never sends the key.
+        .or_else(|| {
+            (matches.get_flag("dry_run") || auth_scheme == http::AuthScheme::None)
+                .then(String::new)
+        })
+        .context("XENDIT_API_KEY missing")?;
+
+    let client_opts = http::ClientOptions {
+        rate_limit: matches.get_one::<f64>("rate_limit").copied(),
+        method_override: matches
+            .get_many::<String>("method_override")
+            .into_iter()
+            .flatten()
+            .map(|verb| verb.trim().to_ascii_uppercase())
+            .collect(),
+        adaptive_rate_limit: matches.get_flag("adaptive_rate_limit"),
+        retries: flag_or_policy(matches, "retries", policy.and_then(|policy| policy.retries))
+            .unwrap_or(0),
+        retry_all: matches.get_flag("retry_all"),
+        retry_backoff: Duration::from_millis(
+            flag_or_policy(
+                matches,
+                "retry_backoff_ms",
+                policy.and_then(|policy| policy.retry_backoff_ms),
+            )
+            .unwrap_or(500),
+        ),
+        timeout: resolve_timeout(matches, policy)?,
+        connect_timeout: matches
+            .get_one::<u64>("connect_timeout_secs")
+            .map(|secs| Duration::from_secs(*secs)),
+        verbose: matches.get_count("verbose"),
+        redact: redact_set(matches),
+        auth_scheme,
+        proxy: matches.get_one::<String>("proxy").cloned(),
+        insecure: matches.get_flag("insecure"),
+        cacert: matches.get_one::<String>("cacert").map(PathBuf::from),
+        rate_limit_wait: !matches.get_flag("no_rate_limit_wait"),
+        cache_ttl: matches
+            .get_one::<u64>("cache_ttl")
+            // A cached GET would never change between polls.
+            .filter(|_| !matches.get_flag("no_cache") && !matches.contains_id("poll_until"))
+            .map(|secs| Duration::from_secs(*secs)),
+        max_rate_retries: matches
+            .get_one::<u32>("max_rate_retries")
+            .copied()
+            .unwrap_or(3),
+        for_user_id: matches
+            .get_one::<String>("for_user_id")
+            .cloned()
+            .or_else(|| env::var("XENDIT_FOR_USER_ID").ok())
+            // An empty value (e.g. an unset CI variable) means no sub-account.
+            .filter(|id| !id.trim().is_empty()),
+        api_version: api_version.map(|(value, _)| (tree.version_header().to_string(), value)),
+        max_redirects: matches
+            .get_one::<usize>("max_redirects")
+            .copied()
+            .unwrap_or(10),
+        retry_log: matches.get_one::<String>("retry_log").map(PathBuf::from),
+    };
+    http::HttpClient::new(base_url, api_key, &client_opts)
+}
+
+fn redact_set(matches: &clap::ArgMatches) -> redact::RedactSet {
+    redact::RedactSet::parse(
+        matches
+            .get_one::<String>("header_redact")
+            .map_or("", String::as_str),
+    )
+}
+
+/// DELETE operations, and POST with `--confirm-post`, ask before sending.
+fn needs_confirmation(op: &Operation, matches: &clap::ArgMatches) -> bool {
+    op.method.eq_ignore_ascii_case("DELETE")
+        || (op.method.eq_ignore_ascii_case("POST") && matches.get_flag("confirm_post"))
+}
+
+/// In production (`--env production` or a `production = true` profile), `destructive`
+/// calls need `--yes` or a yes on the terminal; `--no-confirm` doesn't count there.
+/// Elsewhere, calls that `need_confirm` take `--yes`, `--no-confirm` or a yes on the
+/// terminal. Without a terminal to ask on, both fail instead of waiting for input.
+fn confirm_operation(
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+    destructive: bool,
+    need_confirm: bool,
+    target: &str,
+) -> Result<()> {
+    let production = profile.is_production();
+    if matches.get_flag("yes") {
+        return Ok(());
+    }
+    let question = if production && destructive {
+        if !prompt::available() {
+            return Err(anyhow!(
+                "refusing {target} against production without --yes (no terminal to confirm)"
+            ));
+        }
+        format!("production: {target}. Continue?")
+    } else {
+        if !need_confirm || matches.get_flag("no_confirm") {
+            return Ok(());
+        }
+        if !prompt::available() {
+            return Err(anyhow!(
+                "refusing {target} without --yes or --no-confirm (no terminal to confirm)"
+            ));
+        }
+        format!("{target}. Are you sure?")
+    };
+    if prompt::confirm(&question)? {
+        Ok(())
+    } else {
+        Err(anyhow!("aborted"))
+    }
+}
+
+/// The usual `resolve_setting` order ending at the command tree, except that
+/// `--prefer-profile-base-url` puts the profile's `base_url` right after `--base-url`.
+fn resolve_base_url(
+    tree: &CommandTree,
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+) -> (String, &'static str) {
+    if matches.get_flag("prefer_profile_base_url")
+        && !matches.contains_id("base_url")
+        && let Some(base_url) = &profile.base_url
+    {
+        return (base_url.clone(), "profile (preferred)");
+    }
+    resolve_setting(
+        matches,
+        "base_url",
+        "XENDIT_API_URL",
+        profile
+            .environment
+            .as_ref()
+            .map(|env| (env.name.as_str(), &env.base_url)),
+        &profile.base_url,
+    )
+    .unwrap_or_else(|| (tree.base_url.clone(), "command tree"))
+}
+
+/// Explicit flag > `--env` scoped setting > env var > profile; returns the value and where
+/// it came from. `environment` is the selected env's name and its config value; the scoped
+/// variable is `env_name` suffixed with it, e.g. `XENDIT_API_KEY_PRODUCTION`.
+fn resolve_setting(
+    matches: &clap::ArgMatches,
+    id: &str,
+    env_name: &str,
+    environment: Option<(&str, &Option<String>)>,
+    profile_value: &Option<String>,
+) -> Option<(String, &'static str)> {
+    if let Some(value) = matches.get_one::<String>(id) {
+        return Some((value.clone(), "flag"));
+    }
+    if let Some((name, config_value)) = environment {
+        if let Ok(value) = env::var(format!("{env_name}_{}", name.to_ascii_uppercase())) {
+            return Some((value, "env (scoped)"));
+        }
+        if let Some(value) = config_value {
+            return Some((value.clone(), "environment"));
+        }
+    }
+    if let Ok(value) = env::var(env_name) {
+        return Some((value, "env"));
+    }
+    profile_value.clone().map(|value| (value, "profile"))
+}
+
+/// Resolves the API key: `--api-key` > `--api-key-file` > `--api-key-command` >
+/// `XENDIT_API_KEY` > profile.
+fn resolve_api_key(
+    matches: &clap::ArgMatches,
+    profile: &config::Profile,
+) -> Result<Option<(String, &'static str)>> {
+    if matches.get_one::<String>("api_key").is_none() {
+        if let Some(path) = matches.get_one::<String>("api_key_file") {
+            let key =
+                fs::read_to_string(path).with_context(|| format!("read --api-key-file {path}"))?;
+            return non_empty_key(key, "file", "--api-key-file").map(Some);
+        }
+        if let Some(command) = matches.get_one::<String>("api_key_command") {
+            // stdin/stderr stay attached so password managers can prompt.
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .stdin(std::process::Stdio::inherit())
+                .stderr(std::process::Stdio::inherit())
+                .output()
+                .with_context(|| format!("spawn --api-key-command `{command}`"))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "--api-key-command `{command}` failed: {}",
+                    output.status
+                ));
+            }
+            let key = String::from_utf8(output.stdout)
+                .context("--api-key-command output is not UTF-8")?;
+            return non_empty_key(key, "command", "--api-key-command").map(Some);
+        }
+    }
+    Ok(resolve_setting(
+        matches,
+        "api_key",
+        "XENDIT_API_KEY",
+        profile.environment.as_ref().map(|env| (env.name.as_str(), &env.api_key)),
+        &profile.api_key,
+    ))
+}
+
+fn non_empty_key(
+    key: String,
+    source: &'static str,
+    flag: &str,
+) -> Result<(String, &'static str)> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(anyhow!("{flag} produced an empty API key"));
+    }
+    Ok((key.to_string(), source))
+}
+
+/// Parses `Name: Value`, splitting on the first colon only so values may contain colons.
+fn parse_header_arg(raw: &str) -> Result<(String, String)> {
+    let (name, value) = raw
+        .split_once(':')
+        .ok_or_else(|| anyhow!("invalid --header {raw:?} (expected \"Name: Value\")"))?;
+    let name = name.trim();
+    reqwest::header::HeaderName::from_bytes(name.as_bytes())
+        .with_context(|| format!("invalid header name {name:?}"))?;
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+/// `--idempotency-key`, or a fresh UUID with `--auto-idempotency`; POST only.
+fn idempotency_key(op: &Operation, matches: &clap::ArgMatches) -> Option<String> {
+    let explicit = matches.get_one::<String>("idempotency_key").cloned();
+    if explicit.is_none() && !matches.get_flag("auto_idempotency") {
+        return None;
+    }
+    if !op.method.eq_ignore_ascii_case("POST") {
+        eprintln!(
+            "warning: idempotency key ignored for {} {}",
+            op.method.to_ascii_uppercase(),
+            op.name
+        );
+        return None;
+    }
+    Some(explicit.unwrap_or_else(|| {
+        let key = uuid::Uuid::new_v4().to_string();
+        eprintln!("idempotency-key: {key}");
+        key
+    }))
+}
+
+/// `--timeout-secs` > `XENDIT_TIMEOUT` > 30s default; 0 disables the timeout.
+fn resolve_timeout(
+    matches: &clap::ArgMatches,
+    policy: Option<&config::Policy>,
+) -> Result<Option<Duration>> {
+    let secs = match matches.get_one::<u64>("timeout_secs") {
+        Some(secs) => *secs,
+        None => match env::var("XENDIT_TIMEOUT") {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid XENDIT_TIMEOUT {value:?}"))?,
+            Err(_) => policy
+                .and_then(|policy| policy.timeout_secs)
+                .unwrap_or(DEFAULT_TIMEOUT_SECS),
+        },
+    };
+    Ok((secs > 0).then_some(Duration::from_secs(secs)))
+}
+
+/// A flag with a default value yields to the `--policy` setting unless it was given.
+fn flag_or_policy<T: Clone + Send + Sync + 'static>(
+    matches: &clap::ArgMatches,
+    id: &str,
+    policy: Option<T>,
+) -> Option<T> {
+    if matches.value_source(id) == Some(ValueSource::DefaultValue) && policy.is_some() {
+        return policy;
+    }
+    matches.get_one::<T>(id).cloned().or(policy)
+}
+
+fn handle_stats(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let ops: Vec<&Operation> = tree.resources.iter().flat_map(|res| &res.ops).collect();
+    let mut by_method: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_location: BTreeMap<String, usize> = BTreeMap::new();
+    for op in &ops {
+        *by_method.entry(op.method.to_ascii_uppercase()).or_default() += 1;
+        for param in &op.params {
+            *by_location.entry(param.location.clone()).or_default() += 1;
+        }
+    }
+    let params = ops.iter().map(|op| op.params.len()).sum::<usize>();
+    let required_params = ops
+        .iter()
+        .flat_map(|op| &op.params)
+        .filter(|param| param.required)
+        .count();
+    let with_body = ops.iter().filter(|op| op.has_body).count();
+    let read_only = ops.iter().filter(|op| op.is_safe()).count();
+
+    if let Some(format) = structured_format(matches)? {
+        let out = serde_json::json!({
+            "resources": tree.resources.len(),
+            "operations": ops.len(),
+            "read_only": read_only,
+            "mutating": ops.len() - read_only,
+            "with_body": with_body,
+            "by_method": by_method,
+            "params": params,
+            "required_params": required_params,
+            "params_by_location": by_location,
+        });
+        write_stdout_line(&render::render_as(&out, format, true, &[])?)?;
+        return Ok(());
+    }
+
+    write_stdout_line(&format!("resources: {}", tree.resources.len()))?;
+    write_stdout_line(&format!(
+        "operations: {} ({} read-only, {} mutating, {} with body)",
+        ops.len(),
+        read_only,
+        ops.len() - read_only,
+        with_body
+    ))?;
+    write_stdout_line("by method:")?;
+    for (method, count) in &by_method {
+        write_stdout_line(&format!("  {method}: {count}"))?;
+    }
+    write_stdout_line(&format!("params: {params} ({required_params} required)"))?;
+    for (location, count) in &by_location {
+        write_stdout_line(&format!("  {location}: {count}"))?;
+    }
+    Ok(())
+}
+
+/// Prints the effective profile, base URL and (masked) API key with their sources.
+fn handle_config(
+    tree: &CommandTree,
+    active: &config::ActiveProfile,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let profile = &active.profile;
+    let (base_url, base_url_source) = resolve_base_url(tree, profile, matches);
+    let api_key = resolve_api_key(matches, profile)?
+        .map(|(key, source)| (redact::redact_secret(&key), source));
+    let path = active.path.as_ref().map(|path| path.display().to_string());
+    let environment = profile.environment.as_ref().map(|env| env.name.as_str());
+
+    if let Some(format) = structured_format(matches)? {
+        let out = serde_json::json!({
+            "config_file": path,
+            "profile": active.name,
+            "env": environment,
+            "base_url": {"value": base_url, "source": base_url_source},
+            "api_key": api_key
+                .as_ref()
+                .map(|(key, source)| serde_json::json!({"value": key, "source": source})),
+            "flags": profile.flags,
+        });
+        write_stdout_line(&render::render_as(&out, format, true, &[])?)?;
+        return Ok(());
+    }
+
+    write_stdout_line(&format!(
+        "config file: {}",
+        path.as_deref().unwrap_or("(none)")
+    ))?;
+    write_stdout_line(&format!(
+        "profile: {}",
+        active.name.as_deref().unwrap_or("(none)")
+    ))?;
+    write_stdout_line(&format!("env: {}", environment.unwrap_or("(none)")))?;
+    write_stdout_line(&format!("base_url: {base_url} ({base_url_source})"))?;
+    match &api_key {
+        Some((key, source)) => write_stdout_line(&format!("api_key: {key} ({source})"))?,
+        None => write_stdout_line("api_key: (missing)")?,
+    }
+    if !profile.flags.is_empty() {
+        write_stdout_line(&format!("flags: {}", profile.flags.join(" ")))?;
+    }
+    Ok(())
+}
+
+/// `--config-check`: lists every problem in the config file and fails if there are any.
+fn handle_config_check() -> Result<()> {
+    let path = config::config_path().context("no config dir (set HOME or XDG_CONFIG_HOME)")?;
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            write_stdout_line(&format!("{}: no config file", path.display()))?;
+            return Ok(());
+        }
+        Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+    };
+    let problems = config::check(&raw);
+    if problems.is_empty() {
+        write_stdout_line(&format!("{}: ok", path.display()))?;
+        return Ok(());
+    }
+    for problem in &problems {
+        write_stdout_line(&format!("{}: {problem}", path.display()))?;
+    }
+    Err(anyhow!(
+        "{} problem(s) in {}",
+        problems.len(),
+        path.display()
+    ))
+}
+
+/// Runs NDJSON lines of `{resource, op, params, body}` on a bounded pool of threads
+/// sharing one client. Results stream out in input order, tagged with their input line.
+fn handle_batch(
+    tree: &CommandTree,
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+) -> Result<()> {
+    let raw = match matches.get_one::<String>("file") {
+        Some(path) if path != "-" => {
+            fs::read_to_string(path).with_context(|| format!("read {path}"))?
+        }
+        _ => {
+            let mut raw = String::new();
+            std::io::stdin()
+                .read_to_string(&mut raw)
+                .context("read batch from stdin")?;
+            raw
+        }
+    };
+    let entries: Vec<(usize, &str)> = raw
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let concurrency = matches
+        .get_one::<usize>("concurrency")
+        .copied()
+        .unwrap_or(4)
+        .clamp(1, entries.len().max(1));
+
+    let client = build_client(tree, profile, matches, true)?;
+    // Entries aren't parsed yet, so a production batch is treated as destructive. Elsewhere
+    // it asks when any entry names an op that would ask on its own.
+    let need_confirm = entries.iter().any(|(_, text)| {
+        serde_json::from_str::<Value>(text).is_ok_and(|entry| {
+            let field = |name: &str| entry.get(name).and_then(Value::as_str).unwrap_or_default();
+            find_op(tree, field("resource"), field("op"))
+                .is_some_and(|op| needs_confirmation(op, matches))
+        })
+    });
+    confirm_operation(
+        profile,
+        matches,
+        true,
+        need_confirm,
+        &format!("batch of {} operation(s)", entries.len()),
+    )?;
+    let captures = capture::Captures::default();
+    let headers = matches
+        .get_many::<String>("header")
+        .into_iter()
+        .flatten()
+        .map(|raw| parse_header_arg(raw))
+        .collect::<Result<Vec<_>>>()?;
+
+    let timing = matches.get_flag("timing");
+    let deprecation_warnings = !matches.get_flag("no_deprecation_warnings");
+    let history_env = (!matches.get_flag("no_history") && profile.history != Some(false))
+        .then(|| profile.environment.as_ref().map(|env| env.name.as_str()));
+    let failed = AtomicUsize::new(0);
+    // --timing aggregates over entries that got a response.
+    let (timed, total_ms, max_ms, bytes) = (
+        AtomicUsize::new(0),
+        AtomicU64::new(0),
+        AtomicU64::new(0),
+        AtomicUsize::new(0),
+    );
+    let run = |&(line, text): &(usize, &str)| match run_batch_entry(
+        tree,
+        &client,
+        &captures,
+        &headers,
+        text,
+        deprecation_warnings,
+        history_env,
+    ) {
+        Ok(resp) => {
+            if !resp.is_success() {
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+            let mut out =
+                serde_json::json!({"line": line, "status": resp.status, "body": resp.body});
+            if timing {
+                let elapsed_ms = resp.elapsed.as_millis() as u64;
+                timed.fetch_add(1, Ordering::Relaxed);
+                total_ms.fetch_add(elapsed_ms, Ordering::Relaxed);
+                max_ms.fetch_max(elapsed_ms, Ordering::Relaxed);
+                bytes.fetch_add(resp.raw.len(), Ordering::Relaxed);
+                out["elapsed_ms"] = serde_json::json!(elapsed_ms);
+                out["bytes"] = serde_json::json!(resp.raw.len());
+            }
+            out
+        }
+        Err(err) => {
+            failed.fetch_add(1, Ordering::Relaxed);
+            serde_json::json!({"line": line, "error": format!("{err:#}")})
+        }
+    };
+    // Lines come out in input order however the responses interleave.
+    batch::run_ordered(&entries, concurrency, run, |out| {
+        write_stdout_line(&serde_json::to_string(&out)?)
+    })?;
+
+    let failed = failed.into_inner();
+    eprintln!("batch: {} ok, {failed} failed", entries.len() - failed);
+    let timed = timed.into_inner();
+    if timing && timed > 0 {
+        let total_ms = total_ms.into_inner();
+        eprintln!(
+            "timing: {timed} responses in {total_ms}ms total (mean {}ms, max {}ms), {} bytes",
+            total_ms / timed as u64,
+            max_ms.into_inner(),
+            bytes.into_inner()
         );
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} batch operations failed", entries.len()));
+    }
+    Ok(())
+}
+
+fn run_batch_entry(
+    tree: &CommandTree,
+    client: &http::HttpClient,
+    captures: &capture::Captures,
+    headers: &[(String, String)],
+    text: &str,
+    deprecation_warnings: bool,
+    // `Some(--env)` records the entry in the history log, like a single run.
+    history_env: Option<Option<&str>>,
+) -> Result<http::HttpResponse> {
+    let entry: Value = serde_json::from_str(text).context("invalid JSON line")?;
+    let field = |name: &str| {
+        entry
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing \"{name}\""))
+    };
+    let (res_name, op_name) = (field("resource")?, field("op")?);
+    let op = find_op(tree, res_name, op_name)
+        .ok_or_else(|| unknown_command(tree, res_name, op_name))?;
+    if deprecation_warnings {
+        warn_deprecated(res_name, op);
+    }
+    let mut history =
+        history::Recorder::start(res_name, op, history_env.flatten(), history_env.is_some());
+    // Same shape as a command-tree example: `params` by name plus an optional `body`.
+    let (path, query, param_headers) =
+        build_request_parts(op, None, captures, None, Some(&entry), false)?;
+    if op.is_multipart() {
+        return Err(anyhow!("{res_name} {op_name} takes a multipart body; run it directly"));
+    }
+    let mut body = if op.has_body {
+        entry.get("body").cloned()
+    } else {
+        None
+    };
+    if let Some(body) = body.as_mut() {
+        captures.expand_value(body)?;
+    }
+    let body = body.map(|body| json_body(op, body, false));
+    let headers: Vec<_> = param_headers.into_iter().chain(headers.iter().cloned()).collect();
+    let resp = client.execute(op, &path, &query, &headers, body)?;
+    history.responded(Some(resp.status));
+    Ok(resp)
+}
+
+/// `--since-id ID`: starts the listing after ID by setting the op's id cursor query param
+/// (`--cursor-param`, default `after_id`); `--paginate` then advances from there.
+fn apply_since_id(
+    op: &Operation,
+    matches: &clap::ArgMatches,
+    query: &mut Vec<(String, String)>,
+    since_id: &str,
+) -> Result<()> {
+    let cursor_param = matches
+        .get_one::<String>("cursor_param")
+        .map_or("after_id", String::as_str);
+    if !op
+        .params
+        .iter()
+        .any(|param| param.location == "query" && param.name == cursor_param)
+    {
+        return Err(anyhow!(
+            "--since-id: {} has no {cursor_param} query param (see --cursor-param)",
+            op.name
+        ));
+    }
+    query.retain(|(name, _)| name != cursor_param);
+    query.push((cursor_param.to_string(), since_id.to_string()));
+    Ok(())
+}
+
+/// Wraps a JSON body with the op's content type; `--merge` always sends a merge patch.
+fn json_body(op: &Operation, body: Value, merge: bool) -> http::Body {
+    if merge || op.is_merge_patch() {
+        http::Body::MergePatch(body)
+    } else {
+        http::Body::Json(body)
+    }
+}
+
+fn handle_verify_webhook(matches: &clap::ArgMatches) -> Result<()> {
+    let expected = matches
+        .get_one::<String>("token")
+        .cloned()
+        .or_else(|| env::var("XENDIT_WEBHOOK_TOKEN").ok())
+        .context("verification token missing (--token or XENDIT_WEBHOOK_TOKEN)")?;
+    // Received headers come through the global --header (`name: value` or `name=value`).
+    let headers: Vec<String> = matches
+        .get_many::<String>("header")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    let received = webhook::header_value(&headers, webhook::CALLBACK_TOKEN_HEADER)
+        .map(str::to_string)
+        .or_else(|| env::var("XENDIT_CALLBACK_TOKEN").ok())
+        .context("received token missing (--header x-callback-token=... or XENDIT_CALLBACK_TOKEN)")?;
+
+    // The payload isn't part of the check, but a truncated or garbled one is still an error.
+    let raw = read_body_source(matches.get_one::<String>("body").map_or("-", String::as_str))?;
+    serde_json::from_str::<Value>(&raw).context("invalid JSON webhook payload")?;
+
+    let valid = webhook::token_matches(&expected, &received);
+    let callback_id = matches.get_one::<String>("callback_id");
+    if matches.get_flag("json") {
+        let out = serde_json::json!({"valid": valid, "callback_id": callback_id});
+        write_stdout_line(&serde_json::to_string(&out)?)?;
+    } else if valid {
+        write_stdout_line("valid")?;
+    }
+    if !valid {
+        return Err(anyhow!("x-callback-token does not match the verification token"));
+    }
+    Ok(())
+}
+
+/// The active tree was already validated at startup; a PATH argument checks another file.
+fn handle_validate_schema(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let loaded;
+    let (tree, label) = match matches.get_one::<String>("path") {
+        Some(path) => {
+            loaded = command_tree::load_command_tree_file(Path::new(path))?;
+            command_tree::validate_command_tree(&loaded)?;
+            (&loaded, path.as_str())
+        }
+        None => (tree, "active command tree"),
+    };
+    let ops: usize = tree.resources.iter().map(|res| res.ops.len()).sum();
+    write_stdout_line(&format!(
+        "{label}: ok ({} resources, {ops} operations)",
+        tree.resources.len()
+    ))
+}
+
+fn handle_diff_schema(matches: &clap::ArgMatches) -> Result<()> {
+    let load = |id: &str| -> Result<CommandTree> {
+        let path = matches
+            .get_one::<String>(id)
+            .ok_or_else(|| anyhow!("{id} required"))?;
+        command_tree::load_command_tree_file(Path::new(path))
+    };
+    let changes = schema_diff::diff(&load("old")?, &load("new")?);
+    if let Some(format) = structured_format(matches)? {
+        return write_stdout_line(&render::render_as(&changes.to_json(), format, true, &[])?);
+    }
+    if changes.is_empty() {
+        return write_stdout_line("no changes");
+    }
+    write_stdout_line(&changes.to_lines().join("\n"))
+}
+
+fn handle_generate(matches: &clap::ArgMatches) -> Result<()> {
+    let spec = matches
+        .get_one::<String>("openapi")
+        .ok_or_else(|| anyhow!("--openapi required"))?;
+    let tree = openapi::generate(Path::new(spec))?;
+    let json = serde_json::to_string_pretty(&tree)?;
+    match matches.get_one::<String>("out") {
+        Some(out) => {
+            fs::write(out, format!("{json}\n")).with_context(|| format!("write {out}"))?;
+            write_stdout_line(out)
+        }
+        None => write_stdout_line(&json),
+    }
+}
+
+/// `--out` ending in `.md` gets a single page; any other path is a directory of pages.
+fn handle_docs(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let Some(out) = matches.get_one::<String>("out").map(PathBuf::from) else {
+        return write_stdout_line(docs::single_page(tree).trim_end());
+    };
+    if out.extension().is_some_and(|ext| ext == "md") {
+        fs::write(&out, docs::single_page(tree))
+            .with_context(|| format!("write {}", out.display()))?;
+        return write_stdout_line(&out.display().to_string());
+    }
+    for path in docs::write_pages(tree, &out)? {
+        write_stdout_line(&path.display().to_string())?;
+    }
+    Ok(())
+}
+
+fn handle_export_openapi(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let json = serde_json::to_string_pretty(&openapi::export(tree))?;
+    match matches.get_one::<String>("out") {
+        Some(out) => {
+            fs::write(out, format!("{json}\n")).with_context(|| format!("write {out}"))?;
+            write_stdout_line(out)
+        }
+        None => write_stdout_line(&json),
+    }
+}
+
+fn handle_cache(matches: &clap::ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("clear", _)) => {
+            let removed = cache::clear()?;
+            write_stdout_line(&format!("removed {removed} cached response(s)"))
+        }
+        _ => Err(anyhow!("cache subcommand required")),
+    }
+}
+
+fn handle_history(matches: &clap::ArgMatches) -> Result<()> {
+    let limit = matches.get_one::<usize>("limit").copied().unwrap_or(20);
+    let entries = history::read(limit)?;
+    if let Some(format) = structured_format(matches)? {
+        let value = Value::Array(entries);
+        return write_stdout_line(&render::render_as(&value, format, true, &[])?);
+    }
+    let field = |entry: &Value, name: &str| match entry.get(name) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    for entry in &entries {
+        let status = match field(entry, "status") {
+            status if !status.is_empty() => status,
+            _ if entry.get("error") == Some(&Value::Bool(true)) => "error".to_string(),
+            _ => "ok".to_string(),
+        };
+        let env = match field(entry, "env") {
+            env if env.is_empty() => env,
+            env => format!("[{env}] "),
+        };
+        write_stdout_line(&format!(
+            "{}  {status:>5}  {:>6}ms  {env}{} {} {}",
+            field(entry, "ts"),
+            field(entry, "duration_ms"),
+            field(entry, "method"),
+            field(entry, "resource"),
+            field(entry, "op"),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Completions are generated from the same tree-driven `Command`, so they cover every
+/// resource, operation, and flag.
+fn handle_completions(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let shell = *matches
+        .get_one::<clap_complete::Shell>("shell")
+        .ok_or_else(|| anyhow!("shell required"))?;
+    let mut cmd = build_cli(tree);
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
+    Ok(())
+}
+
+fn handle_watch(
+    tree: &CommandTree,
+    profile: &config::Profile,
+    matches: &clap::ArgMatches,
+    watch_matches: &clap::ArgMatches,
+) -> Result<()> {
+    let interval = watch_matches
+        .get_one::<f64>("interval")
+        .copied()
+        .unwrap_or(5.0);
+    if !interval.is_finite() || interval <= 0.0 {
+        return Err(anyhow!("--interval must be positive"));
+    }
+    let count = watch_matches.get_one::<u64>("count").copied();
+    let tty = std::io::stdout().is_terminal();
+
+    let (res_name, res_matches) = watch_matches
+        .subcommand()
+        .ok_or_else(|| anyhow!("resource required"))?;
+    let (op_name, _) = res_matches
+        .subcommand()
+        .ok_or_else(|| anyhow!("operation required"))?;
+    let op = find_op(tree, res_name, op_name)
+        .ok_or_else(|| unknown_command(tree, res_name, op_name))?;
+    // Every tick sends the request again, so only reads may be watched. Those never ask
+    // for confirmation, which leaves just the banner to show once up front.
+    if !op.is_safe() {
+        return Err(anyhow!(
+            "watch only re-runs GET/HEAD operations; {res_name} {op_name} is {}",
+            op.method.to_ascii_uppercase()
+        ));
+    }
+    if profile.is_production() && !matches.get_flag("no_banner") {
+        production_banner(&resolve_base_url(tree, profile, matches).0);
+    }
+
+    let mut ticks = 0;
+    loop {
+        if tty {
+            // Clear the screen and home the cursor before each re-render.
+            print!("\x1b[2J\x1b[H");
+        } else {
+            write_stdout_line(&format!("# {}", clock::now_rfc3339()))?;
+        }
+        // A failed tick is reported but doesn't stop the watch.
+        if let Err(err) = run_operation(tree, profile, matches, watch_matches, false) {
+            eprintln!("error: {err}");
+        }
+        ticks += 1;
+        if count.is_some_and(|count| ticks >= count) {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs_f64(interval));
+    }
+}
+
+fn poll_options(op: &Operation, matches: &clap::ArgMatches) -> Result<Option<poll::PollOptions>> {
+    let Some(expr) = matches.get_one::<String>("poll_until") else {
+        return Ok(None);
+    };
+    if !op.is_safe() || op.has_body {
+        return Err(anyhow!(
+            "--poll-until only applies to GET operations without a body"
+        ));
+    }
+    let seconds = |id: &str, flag: &str| -> Result<Duration> {
+        let secs = matches.get_one::<f64>(id).copied().unwrap_or_default();
+        if !secs.is_finite() || secs <= 0.0 {
+            return Err(anyhow!("{flag} must be positive"));
+        }
+        Ok(Duration::from_secs_f64(secs))
+    };
+    Ok(Some(poll::PollOptions {
+        until: select::Condition::parse(expr)?,
+        interval: seconds("poll_interval", "--poll-interval")?,
+        timeout: seconds("poll_timeout", "--poll-timeout")?,
+        quiet: matches.get_flag("quiet"),
+    }))
+}
+
+/// Replaces a config alias in command position with its words. Aliases may not shadow a
+/// built-in command or resource, so a real command name always means the real command.
+fn expand_alias(
+    cli: &Command,
+    aliases: &BTreeMap<String, String>,
+    args: Vec<String>,
+) -> Result<Vec<String>> {
+    if let Some(alias) = aliases.keys().find(|alias| cli.find_subcommand(alias).is_some()) {
+        return Err(anyhow!(
+            "alias {alias} collides with the `{alias}` command; rename it in the config file"
+        ));
+    }
+    // Values of global flags must not be mistaken for the command word.
+    let takes_value = |arg: &str| {
+        cli.get_arguments().any(|def| {
+            def.get_action().takes_values()
+                && match arg.strip_prefix("--") {
+                    Some(long) => def.get_long() == Some(long),
+                    None => arg.len() == 2 && arg[1..].chars().next() == def.get_short(),
+                }
+        })
+    };
+    let mut idx = 0;
+    while let Some(arg) = args.get(idx) {
+        if arg == "--" {
+            break;
+        }
+        if !arg.starts_with('-') {
+            let Some(expansion) = aliases.get(arg) else {
+                break;
+            };
+            let words: Vec<String> = expansion.split_whitespace().map(str::to_string).collect();
+            if words.is_empty() {
+                return Err(anyhow!("alias {arg} is empty"));
+            }
+            let mut out = args[..idx].to_vec();
+            out.extend(words);
+            out.extend_from_slice(&args[idx + 1..]);
+            return Ok(out);
+        }
+        idx += if takes_value(arg) { 2 } else { 1 };
+    }
+    Ok(args)
+}
+
+/// Reads `--flag VALUE` / `--flag=VALUE` from argv ahead of clap.
+fn early_flag_value(flag: &str) -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+fn build_cli(tree: &CommandTree) -> Command {
+    let mut cmd = Command::new("xendit")
+        .about("Xendit CLI (auto-generated)")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(render::OutputFormat::NAMES)
+                .default_value("json")
+                .help("Output format (env: KEY='value' lines for eval; table/csv: columns for lists)"),
+        )
+        .arg(
+            Arg::new("output_file")
+                .long("output-file")
+                .global(true)
+                .value_name("PATH")
+                .help("Write the rendered response to PATH instead of stdout"),
+        )
+        .arg(
+            Arg::new("error_format")
+                .long("error-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .help("How failures are reported on stderr (json: {\"error\": {status, code, message, request_id}})"),
+        )
+        .arg(
+            Arg::new("download")
+                .long("download")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Save the raw response body to --output-file, else the Content-Disposition name"),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["paginate", "download"])
+                .help("Print a top-level JSON array one element per line as it arrives (constant memory)"),
+        )
+        .arg(
+            Arg::new("poll_until")
+                .long("poll-until")
+                .global(true)
+                .value_name("COND")
+                .conflicts_with_all(["paginate", "stream"])
+                .help("Re-run a GET until COND holds, e.g. '.status == \"COMPLETED\"'"),
+        )
+        .arg(
+            Arg::new("poll_interval")
+                .long("poll-interval")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("5")
+                .help("Seconds between --poll-until requests"),
+        )
+        .arg(
+            Arg::new("poll_timeout")
+                .long("poll-timeout")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("300")
+                .help("Give up on --poll-until after SECONDS (exits nonzero)"),
+        )
+        .arg(
+            Arg::new("select")
+                .long("select")
+                .global(true)
+                .value_name("EXPR")
+                .help("Select part of the body, e.g. '.data[].id' or '.data[] | select(.status == \"PAID\")'"),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .global(true)
+                .value_name("KEYS")
+                .value_delimiter(',')
+                .help("Comma-separated columns for --output table/csv (default: every key)"),
+        )
+        .arg(
+            Arg::new("pretty_numbers")
+                .long("pretty-numbers")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Group thousands in integers with --output table (never in JSON/CSV)"),
+        )
+        .arg(
+            Arg::new("locale")
+                .long("locale")
+                .global(true)
+                .value_name("LOCALE")
+                .value_parser(table::LOCALES)
+                .default_value("en")
+                .help("Grouping style for --pretty-numbers: en 1,000 | id 1.000 | fr 1 000 | ch 1'000"),
+        )
+        .arg(
+            Arg::new("pretty")
+                .long("pretty")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Pretty-print JSON output"),
+        )
+        .arg(
+            Arg::new("canonical")
+                .long("canonical")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("pretty")
+                .help("Byte-stable JSON for snapshot tests (compact, sorted keys, normalized numbers)"),
+        )
+        .arg(
+            Arg::new("raw")
+                .long("raw")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Include status and headers"),
+        )
+        .arg(
+            Arg::new("headers_only")
+                .long("headers-only")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("raw")
+                .help("Print only the response headers as JSON"),
+        )
+        .arg(
+            Arg::new("paginate")
+                .long("paginate")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Follow has_more/links pagination and merge all pages' data"),
+        )
+        .arg(
+            Arg::new("cursor_param")
+                .long("cursor-param")
+                .global(true)
+                .value_name("NAME")
+                .default_value("after_id")
+                .help("Query param that carries the pagination cursor"),
+        )
+        .arg(
+            Arg::new("max_pages")
+                .long("max-pages")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("100")
+                .help("Stop paginating after N pages"),
+        )
+        .arg(
+            Arg::new("since_id")
+                .long("since-id")
+                .global(true)
+                .value_name("ID")
+                .help("List items after ID via the op's --cursor-param; with --paginate, follow to the end"),
+        )
+        .arg(
+            Arg::new("limit_output")
+                .long("limit-output")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Truncate the top-level (or `data`) array to N items client-side"),
+        )
+        .arg(
+            Arg::new("header_redact")
+                .long("header-redact")
+                .global(true)
+                .value_name("NAMES")
+                .help("Comma-separated header names to mask in output, on top of Authorization, Proxy-Authorization and Set-Cookie"),
+        )
+        .arg(
+            Arg::new("hyperlinks")
+                .long("hyperlinks")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Render URL values as clickable OSC 8 links in table, yaml and env output on a terminal"),
+        )
+        .arg(
+            Arg::new("no_progress")
+                .long("no-progress")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Never show a spinner for slow requests"),
+        )
+        .arg(
+            Arg::new("transform")
+                .long("transform")
+                .global(true)
+                .value_name("CMD")
+                .help("Pipe rendered output through an external command (runs last)"),
+        )
+        .arg(
+            Arg::new("explain_error")
+                .long("explain-error")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Explain Xendit error codes and suggest a fix on failure"),
+        )
+        .arg(
+            Arg::new("diff_against")
+                .long("diff-against")
+                .global(true)
+                .value_name("FILE")
+                .help("Print only fields that changed relative to a saved baseline response"),
+        )
+        .arg(
+            Arg::new("update_baseline")
+                .long("update-baseline")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("diff_against")
+                .help("Rewrite (or create) the baseline with the current response"),
+        )
+        .arg(
+            Arg::new("fail_on_change")
+                .long("fail-on-change")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .requires("diff_against")
+                .help("Exit non-zero when the response differs from the baseline"),
+        )
+        .arg(
+            Arg::new("assert_schema")
+                .long("assert-schema")
+                .global(true)
+                .value_name("FILE")
+                .help("Fail unless the response body validates against this JSON Schema"),
+        )
+        .arg(
+            Arg::new("assert_status")
+                .long("assert-status")
+                .global(true)
+                .value_name("STATUS")
+                .action(ArgAction::Append)
+                .value_parser(assertions::parse_status_pattern)
+                .help("Fail unless the status matches (201, 2xx; repeatable); a match passes even if not 2xx"),
+        )
+        .arg(
+            Arg::new("assert")
+                .long("assert")
+                .global(true)
+                .value_name("COND")
+                .action(ArgAction::Append)
+                .help("Fail unless COND holds for the body, e.g. '.status == \"PAID\"' (repeatable)"),
+        )
+        .arg(
+            Arg::new("capture")
+                .long("capture")
+                .global(true)
+                .value_name("NAME=PATH")
+                .action(ArgAction::Append)
+                .help("Save a response value (e.g. id=.id) for ${NAME} in later calls"),
+        )
+        .arg(
+            Arg::new("method_override")
+                .long("method-override")
+                .global(true)
+                .value_name("VERBS")
+                .value_delimiter(',')
+                .help("Send these methods (e.g. PATCH,DELETE) as POST with X-HTTP-Method-Override"),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .short('H')
+                .global(true)
+                .value_name("NAME: VALUE")
+                .action(ArgAction::Append)
+                .help("Extra request header (repeatable; may override User-Agent)"),
+        )
+        .arg(
+            Arg::new("api_version")
+                .long("api-version")
+                .global(true)
+                .value_name("DATE")
+                .help("Pin API behavior with a version header (or XENDIT_API_VERSION)"),
+        )
+        .arg(
+            Arg::new("for_user_id")
+                .long("for-user-id")
+                .global(true)
+                .value_name("ID")
+                .help("Act on behalf of a sub-account via the for-user-id header (or XENDIT_FOR_USER_ID)"),
+        )
+        .arg(
+            Arg::new("idempotency_key")
+                .long("idempotency-key")
+                .global(true)
+                .value_name("KEY")
+                .help("Send an Idempotency-key header with POST requests"),
+        )
+        .arg(
+            Arg::new("auto_idempotency")
+                .long("auto-idempotency")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Generate an idempotency key for POST requests (printed to stderr)"),
+        )
+        .arg(
+            Arg::new("timeout_secs")
+                .long("timeout-secs")
+                .global(true)
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Total request timeout (default 30, env XENDIT_TIMEOUT, 0 = none)"),
+        )
+        .arg(
+            Arg::new("connect_timeout_secs")
+                .long("connect-timeout-secs")
+                .global(true)
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Timeout for establishing the connection"),
+        )
+        .arg(
+            Arg::new("max_rate_retries")
+                .long("max-rate-retries")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("3")
+                .help("Times to wait out a 429 (Retry-After, else backoff) before failing"),
+        )
+        .arg(
+            Arg::new("max_redirects")
+                .long("max-redirects")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("Redirects to follow (0 returns the 3xx response); Authorization is dropped across origins"),
+        )
+        .arg(
+            Arg::new("no_rate_limit_wait")
+                .long("no-rate-limit-wait")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Fail immediately on 429 instead of waiting"),
+        )
+        .arg(
+            Arg::new("cache_ttl")
+                .long("cache-ttl")
+                .global(true)
+                .value_name("SECS")
+                .value_parser(clap::value_parser!(u64))
+                .help("Serve successful GET responses from the on-disk cache for SECS seconds"),
+        )
+        .arg(
+            Arg::new("no_cache")
+                .long("no-cache")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Bypass the response cache even if --cache-ttl is set"),
+        )
+        .arg(
+            Arg::new("no_history")
+                .long("no-history")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Don't record this invocation in the history log"),
+        )
+        .arg(
+            Arg::new("no_deprecation_warnings")
+                .long("no-deprecation-warnings")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Don't warn when invoking an operation marked deprecated"),
+        )
+        .arg(
+            Arg::new("proxy")
+                .long("proxy")
+                .global(true)
+                .value_name("URL")
+                .help("Proxy URL (http://, https://, socks5://); default honors HTTP(S)_PROXY/NO_PROXY"),
+        )
+        .arg(
+            Arg::new("insecure")
+                .long("insecure")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .conflicts_with("cacert")
+                .help("Accept invalid TLS certificates (dangerous; testing only)"),
+        )
+        .arg(
+            Arg::new("cacert")
+                .long("cacert")
+                .global(true)
+                .value_name("PEM")
+                .help("Trust an extra PEM root certificate"),
+        )
+        .arg(
+            Arg::new("retries")
+                .long("retries")
+                .global(true)
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32))
+                .default_value("0")
+                .help("Retry GET/HEAD requests on connection errors and 502/503/504"),
+        )
+        .arg(
+            Arg::new("retry_all")
+                .long("retry-all")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Also retry PUT/PATCH/DELETE, and POST with an idempotency key (may apply a write twice)"),
+        )
+        .arg(
+            Arg::new("retry_backoff_ms")
+                .long("retry-backoff-ms")
+                .global(true)
+                .value_name("MS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("500")
+                .help("Base delay for exponential backoff between retries"),
+        )
+        .arg(
+            Arg::new("retry_log")
+                .long("retry-log")
+                .global(true)
+                .value_name("PATH")
+                .help("Append one JSON line per retry or 429 wait to PATH"),
+        )
+        .arg(
+            Arg::new("rate_limit")
+                .long("rate-limit")
+                .global(true)
+                .value_name("REQ_PER_SEC")
+                .value_parser(clap::value_parser!(f64))
+                .help("Client-side request rate cap across all operations"),
+        )
+        .arg(
+            Arg::new("adaptive_rate_limit")
+                .long("adaptive-rate-limit")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Slow down based on X-RateLimit-Remaining/Reset response headers"),
+        )
+        .arg(
+            Arg::new("lossy_numbers")
+                .long("lossy-numbers")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Round response numbers through f64 instead of preserving exact digits"),
+        )
+        .arg(
+            Arg::new("param_file")
+                .long("param-file")
+                .global(true)
+                .value_name("PATH")
+                .help("Read path/query param values from a JSON file (flags still win)"),
+        )
+        .arg(
+            Arg::new("param_file_format")
+                .long("param-file-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(param_file::FORMATS)
+                .default_value("auto")
+                .help("flat {name: value} or scoped {path: {...}, query: {...}}"),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .global(true)
+                .action(ArgAction::Count)
+                .help("Log request/response lines and headers to stderr (-vv adds bodies)"),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Suppress the stdout body; the exit code still reports the outcome"),
+        )
+        .arg(
+            Arg::new("timing")
+                .long("timing")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Report elapsed time, response size and status (stderr, or `meta` with --raw)"),
+        )
+        .arg(
+            Arg::new("dry_run")
+                .long("dry-run")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print the resolved request (method, URL, headers, body) without sending it"),
+        )
+        .arg(
+            Arg::new("print_curl")
+                .long("print-curl")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print the request as a curl command on stderr (key masked); still sends it"),
+        )
+        .arg(
+            Arg::new("unsafe_print_curl")
+                .long("unsafe-print-curl")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Like --print-curl but with the real API key and headers"),
+        )
+        .arg(
+            Arg::new("dump_parsed_body")
+                .long("dump-parsed-body")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print the final request body to stderr before sending"),
+        )
+        .arg(
+            Arg::new("command_tree_dir")
+                .long("command-tree-dir")
+                .global(true)
+                .value_name("DIR")
+                .help("Load and merge command trees from DIR/*.json instead of the embedded tree"),
+        )
+        .arg(
+            Arg::new("schema")
+                .long("schema")
+                .global(true)
+                .value_name("PATH")
+                .conflicts_with("command_tree_dir")
+                .help("Load command_tree.json from PATH instead of the embedded tree (or XENDIT_SCHEMA)"),
+        )
+        .arg(
+            Arg::new("base_url")
+                .long("base-url")
+                .global(true)
+                .value_name("URL")
+                .help("Override base API URL"),
+        )
+        .arg(
+            Arg::new("prefer_profile_base_url")
+                .long("prefer-profile-base-url")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Let the profile's base_url win over XENDIT_API_URL and --env settings"),
+        )
+        .arg(
+            Arg::new("api_key")
+                .long("api-key")
+                .global(true)
+                .value_name("KEY")
+                .help("Override XENDIT_API_KEY"),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .overrides_with("no_interactive")
+                .help("Prompt on the terminal for missing required params and body"),
+        )
+        .arg(
+            Arg::new("no_interactive")
+                .long("no-interactive")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .overrides_with("interactive")
+                .help("Never prompt; missing params fail immediately (overrides a profile's --interactive)"),
+        )
+        .arg(
+            Arg::new("env")
+                .long("env")
+                .global(true)
+                .value_name("ENV")
+                .value_parser(config::ENVIRONMENTS)
+                .help("Use the sandbox or production key/base URL (XENDIT_ENV)"),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Skip every confirmation, including for destructive operations under --env production"),
+        )
+        .arg(
+            Arg::new("no_confirm")
+                .long("no-confirm")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Don't ask before DELETE (or --confirm-post POST); production still needs --yes"),
+        )
+        .arg(
+            Arg::new("no_banner")
+                .long("no-banner")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Don't print the PRODUCTION banner for production profiles and --env production"),
+        )
+        .arg(
+            Arg::new("confirm_post")
+                .long("confirm-post")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Also ask before POST operations, like DELETE"),
+        )
+        .arg(
+            Arg::new("api_key_file")
+                .long("api-key-file")
+                .global(true)
+                .value_name("PATH")
+                .help("Read the API key from PATH (trimmed)"),
+        )
+        .arg(
+            Arg::new("api_key_command")
+                .long("api-key-command")
+                .global(true)
+                .value_name("CMD")
+                .help("Run CMD with sh -c and use its stdout as the API key (e.g. 'pass xendit/key')"),
+        )
+        .arg(
+            Arg::new("auth_scheme")
+                .long("auth-scheme")
+                .global(true)
+                .value_name("SCHEME")
+                .value_parser(http::AuthScheme::NAMES)
+                .default_value("basic")
+                .help("How the API key is sent: basic (Xendit default), bearer, or none"),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .global(true)
+                .value_name("NAME")
+                .help("Profile from ~/.config/xendit/config.toml (or XENDIT_PROFILE)"),
+        )
+        .arg(
+            // Resolved before clap parses, like --profile.
+            Arg::new("policy")
+                .long("policy")
+                .global(true)
+                .value_name("NAME")
+                .help("Timeout/retry defaults from a [policy.NAME] config table; explicit flags win"),
+        )
+        .arg(
+            // Handled before clap parses; declared for --help.
+            Arg::new("config_check")
+                .long("config-check")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Validate ~/.config/xendit/config.toml, list every problem and exit"),
+        );
+
+    cmd = cmd.subcommand(
+        Command::new("list")
+            .about("List resources and operations")
+            .arg(
+                Arg::new("kind")
+                    .long("kind")
+                    .value_name("KIND")
+                    .value_parser(["read", "write"])
+                    .help("Only show read-only (GET/HEAD) or mutating operations"),
+            )
+            .arg(
+                Arg::new("tag")
+                    .long("tag")
+                    .value_name("TAG")
+                    .help("Only show resources tagged TAG (case-insensitive)"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
 
     cmd = cmd.subcommand(
-        Command::new("list")
-            .about("List resources and operations")
+        Command::new("search")
+            .about("Find operations whose resource, name, path or description match TERM")
+            .arg(Arg::new("term").required(true).value_name("TERM"))
             .arg(
                 Arg::new("json")
                     .long("json")
@@ -140,6 +2220,221 @@ fn build_cli(tree: &CommandTree) -> Command {
             ),
     );
 
+    cmd = cmd.subcommand(
+        Command::new("stats")
+            .about("Summarize the command tree (resources, operations, methods, params)")
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("config")
+            .about("Show the resolved profile, base URL and API key (masked)")
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("cache")
+            .about("Manage the GET response cache (--cache-ttl)")
+            .subcommand_required(true)
+            .subcommand(Command::new("clear").about("Delete every cached response")),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("history")
+            .about("Show recent invocations (resource, op, status, duration; never bodies or keys)")
+            .arg(
+                Arg::new("limit")
+                    .long("limit")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("20")
+                    .help("Show the last N invocations"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("batch")
+            .about("Run NDJSON operations ({resource, op, params, body}) concurrently")
+            .arg(
+                Arg::new("file")
+                    .long("file")
+                    .value_name("PATH")
+                    .help("NDJSON file of operations (default or -: stdin)"),
+            )
+            .arg(
+                Arg::new("concurrency")
+                    .long("concurrency")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(usize))
+                    .default_value("4")
+                    .help("Operations in flight at once"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("verify-webhook")
+            .about("Check a webhook callback's x-callback-token against your verification token")
+            .arg(
+                Arg::new("token")
+                    .long("token")
+                    .value_name("TOKEN")
+                    .help("Webhook verification token from the dashboard (or XENDIT_WEBHOOK_TOKEN)"),
+            )
+            .arg(
+                Arg::new("callback_id")
+                    .long("callback-id")
+                    .value_name("ID")
+                    .help("webhook-id of the callback, echoed in the result"),
+            )
+            .arg(
+                Arg::new("body")
+                    .long("body")
+                    .value_name("JSON")
+                    .help("Callback payload (inline, @file.json, or - for stdin; default stdin)"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit {\"valid\": true|false}"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("validate-schema")
+            .about("Check a command tree for duplicate names, bad params and methods")
+            .arg(
+                Arg::new("path")
+                    .value_name("PATH")
+                    .help("command_tree.json to check (default: the active tree)"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("docs")
+            .about("Render the command tree as reference docs")
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .value_parser(docs::FORMATS)
+                    .default_value("markdown")
+                    .help("Output format"),
+            )
+            .arg(
+                Arg::new("out")
+                    .long("out")
+                    .value_name("PATH")
+                    .help("A .md file for one page, or a directory for a page per resource (default: stdout)"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("diff-schema")
+            .about("Compare two command trees: added/removed resources and ops, changed ops")
+            .arg(
+                Arg::new("old")
+                    .required(true)
+                    .value_name("OLD")
+                    .help("Baseline command_tree.json"),
+            )
+            .arg(
+                Arg::new("new")
+                    .required(true)
+                    .value_name("NEW")
+                    .help("Updated command_tree.json"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .action(ArgAction::SetTrue)
+                    .help("Emit machine-readable JSON"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("generate")
+            .about("Generate a command tree from an OpenAPI 3 spec (JSON or YAML)")
+            .arg(
+                Arg::new("openapi")
+                    .long("openapi")
+                    .value_name("SPEC")
+                    .required(true)
+                    .help("OpenAPI document to convert"),
+            )
+            .arg(
+                Arg::new("out")
+                    .long("out")
+                    .value_name("PATH")
+                    .help("Write the tree to PATH instead of stdout"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("export-openapi")
+            .about("Export the command tree as a minimal OpenAPI 3 document (the inverse of generate)")
+            .arg(
+                Arg::new("out")
+                    .long("out")
+                    .value_name("PATH")
+                    .help("Write the spec to PATH instead of stdout"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("completions")
+            .about("Print a shell completion script")
+            .arg(
+                Arg::new("shell")
+                    .required(true)
+                    .value_parser(clap::value_parser!(clap_complete::Shell))
+                    .help("bash, zsh, fish, powershell, or elvish"),
+            ),
+    );
+
+    cmd = cmd.subcommand(
+        Command::new("watch")
+            .about("Re-run an operation on an interval")
+            .subcommand_required(true)
+            .arg(
+                Arg::new("interval")
+                    .long("interval")
+                    .value_name("SECONDS")
+                    .value_parser(clap::value_parser!(f64))
+                    .default_value("5")
+                    .help("Seconds between runs"),
+            )
+            .arg(
+                Arg::new("count")
+                    .long("count")
+                    .value_name("N")
+                    .value_parser(clap::value_parser!(u64))
+                    .help("Stop after N runs (default: until Ctrl-C)"),
+            )
+            .subcommands(build_resource_commands(tree)),
+    );
+
+    cmd.subcommands(build_resource_commands(tree))
+}
+
+fn build_resource_commands(tree: &CommandTree) -> Vec<Command> {
+    let mut commands = Vec::new();
     for resource in &tree.resources {
         let mut res_cmd = Command::new(resource.name.clone())
             .about(resource.name.clone())
@@ -147,49 +2442,200 @@ fn build_cli(tree: &CommandTree) -> Command {
             .arg_required_else_help(true);
 
         for op in &resource.ops {
-            let mut op_cmd = Command::new(op.name.clone()).about(op.path.clone());
+            let about = match op.deprecated {
+                Some(_) => format!("[deprecated] {}", op.path),
+                None => op.path.clone(),
+            };
+            let mut op_cmd = Command::new(op.name.clone()).about(about);
             for param in &op.params {
                 op_cmd = op_cmd.arg(build_param_arg(param));
             }
-            if op.has_body {
+            op_cmd = op_cmd.arg(
+                Arg::new("example_run")
+                    .long("example-run")
+                    .value_name("INDEX")
+                    .num_args(0..=1)
+                    .default_missing_value("0")
+                    .value_parser(clap::value_parser!(usize))
+                    .help("Run with the operation's stored example inputs (default: first)"),
+            );
+            if op.is_multipart() {
+                op_cmd = op_cmd.arg(
+                    Arg::new("form")
+                        .long("form")
+                        .value_name("NAME=VALUE")
+                        .action(ArgAction::Append)
+                        .help("Add a text part to the multipart body"),
+                );
+                op_cmd = op_cmd.arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("NAME=@PATH")
+                        .action(ArgAction::Append)
+                        .help("Attach a file part to the multipart body"),
+                );
+            } else if op.has_body {
                 op_cmd = op_cmd.arg(
                     Arg::new("body")
                         .long("body")
                         .value_name("JSON")
-                        .help("Request body JSON (or @file.json)"),
+                        .help("Request body JSON (or @file.json, or - for stdin)"),
+                );
+                op_cmd = op_cmd.arg(
+                    Arg::new("open_in_editor")
+                        .long("open-in-editor")
+                        .action(ArgAction::SetTrue)
+                        .help("Compose the body in $EDITOR (seeded from --body if given)"),
                 );
+                op_cmd = op_cmd.arg(
+                    Arg::new("field")
+                        .long("field")
+                        .value_name("NAME=VALUE")
+                        .action(ArgAction::Append)
+                        .conflicts_with("body")
+                        .help("Set a body field (dotted names nest; NAME:=JSON for raw values)"),
+                );
+                if op.method.eq_ignore_ascii_case("PATCH") {
+                    op_cmd = op_cmd.arg(
+                        Arg::new("merge")
+                            .long("merge")
+                            .value_name("NAME=VALUE")
+                            .action(ArgAction::Append)
+                            .conflicts_with_all(["body", "field"])
+                            .help("Send a JSON merge patch with only this field (NAME:=null deletes it)"),
+                    );
+                }
+                op_cmd = op_cmd.arg(
+                    Arg::new("field_file")
+                        .long("field-file")
+                        .value_name("NAME=@PATH")
+                        .action(ArgAction::Append)
+                        .help("Base64-encode a file into a JSON body field"),
+                );
+                if op.external_id {
+                    op_cmd = op_cmd.arg(
+                        Arg::new("auto_external_id")
+                            .long("auto-external-id")
+                            .value_name("PREFIX")
+                            .num_args(0..=1)
+                            .default_missing_value("cli-")
+                            .help("Add a unique external_id (PREFIX + uuid) if the body lacks one"),
+                    );
+                }
             }
             res_cmd = res_cmd.subcommand(op_cmd);
         }
-        cmd = cmd.subcommand(res_cmd);
+        commands.push(res_cmd);
     }
-
-    cmd
+    commands
 }
 
 fn handle_list(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
-    if matches.get_flag("json") {
-        let out: Vec<_> = tree
-            .resources
+    let kind = matches.get_one::<String>("kind").map(String::as_str);
+    let keep = |op: &Operation| match kind {
+        Some("read") => op.is_safe(),
+        Some("write") => !op.is_safe(),
+        _ => true,
+    };
+    let tag = matches.get_one::<String>("tag");
+    let resources: Vec<(&Resource, Vec<&Operation>)> = tree
+        .resources
+        .iter()
+        .filter(|res| tag.is_none_or(|tag| res.has_tag(tag)))
+        .map(|res| {
+            let ops: Vec<&Operation> = res.ops.iter().filter(|op| keep(op)).collect();
+            (res, ops)
+        })
+        .filter(|(_, ops)| !ops.is_empty())
+        .collect();
+
+    if let Some(format) = structured_format(matches)? {
+        let out: Vec<_> = resources
             .iter()
-            .map(|res| {
-                let ops: Vec<String> = res.ops.iter().map(|op| op.name.clone()).collect();
-                serde_json::json!({"resource": res.name, "ops": ops})
+            .map(|(res, ops)| {
+                let names = |deprecated: bool| -> Vec<&str> {
+                    ops.iter()
+                        .filter(|op| op.deprecated.is_some() || !deprecated)
+                        .map(|op| op.name.as_str())
+                        .collect()
+                };
+                let mut out = serde_json::json!({
+                    "resource": res.name,
+                    "tags": res.tags,
+                    "ops": names(false),
+                });
+                if ops.iter().any(|op| op.deprecated.is_some()) {
+                    out["deprecated"] = serde_json::json!(names(true));
+                }
+                out
             })
             .collect();
-        write_stdout_line(&serde_json::to_string_pretty(&out)?)?;
+        write_stdout_line(&render::render_as(&Value::Array(out), format, true, &[])?)?;
         return Ok(());
     }
 
-    for res in &tree.resources {
-        write_stdout_line(&res.name)?;
-        for op in &res.ops {
-            write_stdout_line(&format!("  {}", op.name))?;
+    for (res, ops) in &resources {
+        if res.tags.is_empty() {
+            write_stdout_line(&res.name)?;
+        } else {
+            write_stdout_line(&format!("{} ({})", res.name, res.tags.join(", ")))?;
+        }
+        for op in ops {
+            match op.deprecated {
+                Some(_) => write_stdout_line(&format!("  {} [deprecated]", op.name))?,
+                None => write_stdout_line(&format!("  {}", op.name))?,
+            }
         }
     }
     Ok(())
 }
 
+/// Case-insensitive substring match on resource/op names, paths and descriptions.
+fn handle_search(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
+    let term = matches
+        .get_one::<String>("term")
+        .ok_or_else(|| anyhow!("term required"))?
+        .to_lowercase();
+    let hits: Vec<(&str, &Operation)> = tree
+        .resources
+        .iter()
+        .flat_map(|res| res.ops.iter().map(move |op| (res.name.as_str(), op)))
+        .filter(|(resource, op)| {
+            let texts = [
+                Some(*resource),
+                Some(op.name.as_str()),
+                Some(op.path.as_str()),
+                op.description.as_deref(),
+            ];
+            texts
+                .into_iter()
+                .flatten()
+                .any(|text| text.to_lowercase().contains(&term))
+        })
+        .collect();
+
+    if let Some(format) = structured_format(matches)? {
+        let out: Vec<_> = hits
+            .iter()
+            .map(|(resource, op)| {
+                serde_json::json!({
+                    "resource": resource,
+                    "op": op.name,
+                    "method": op.method.to_ascii_uppercase(),
+                    "path": op.path,
+                })
+            })
+            .collect();
+        write_stdout_line(&render::render_as(&Value::Array(out), format, true, &[])?)?;
+        return Ok(());
+    }
+
+    for (resource, op) in &hits {
+        write_stdout_line(&format!("{resource} {}  {}", op.name, op.path))?;
+    }
+    Ok(())
+}
+
 fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
     let resource = matches
         .get_one::<String>("resource")
@@ -199,14 +2645,23 @@ fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()>
         .ok_or_else(|| anyhow!("operation required"))?;
 
     let op = find_op(tree, resource, op_name)
-        .ok_or_else(|| anyhow!("unknown command {resource} {op_name}"))?;
+        .ok_or_else(|| unknown_command(tree, resource, op_name))?;
 
-    if matches.get_flag("json") {
-        write_stdout_line(&serde_json::to_string_pretty(op)?)?;
+    if let Some(format) = structured_format(matches)? {
+        let value = serde_json::to_value(op)?;
+        write_stdout_line(&render::render_as(&value, format, true, &[])?)?;
         return Ok(());
     }
 
-    write_stdout_line(&format!("{} {}", resource, op.name))?;
+    match op.deprecated {
+        Some(_) => write_stdout_line(&format!("{} {} [deprecated]", resource, op.name))?,
+        None => write_stdout_line(&format!("{} {}", resource, op.name))?,
+    }
+    if let Some(reason) = op.deprecated.as_deref().map(str::trim)
+        && !reason.is_empty()
+    {
+        write_stdout_line(&format!("  deprecated: {reason}"))?;
+    }
     write_stdout_line(&format!("  method: {}", op.method))?;
     write_stdout_line(&format!("  path: {}", op.path))?;
     if let Some(desc) = &op.description {
@@ -217,25 +2672,94 @@ fn handle_describe(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()>
     if !op.params.is_empty() {
         write_stdout_line("  params:")?;
         for param in &op.params {
-            let req = if param.required { "required" } else { "optional" };
-            write_stdout_line(&format!("    --{}  {} ({})", param.flag, param.location, req))?;
+            let mut notes = vec![if param.required { "required" } else { "optional" }];
+            if param.value_type() != "string" {
+                notes.push(param.value_type());
+            }
+            if param.multiple {
+                notes.push("repeatable");
+            }
+            write_stdout_line(&format!(
+                "    --{}  {} ({})",
+                param.flag,
+                param.location,
+                notes.join(", ")
+            ))?;
+            if let Some(values) = &param.enum_values {
+                write_stdout_line(&format!("      one of: {}", values.join(", ")))?;
+            }
+            if let Some(default) = &param.default {
+                write_stdout_line(&format!("      default: {default}"))?;
+            }
+            if let Some(pattern) = &param.pattern {
+                write_stdout_line(&format!("      pattern: {pattern}"))?;
+            }
         }
     }
-    if op.has_body {
-        write_stdout_line("  body: --body JSON or @file.json")?;
+    if op.is_multipart() {
+        write_stdout_line("  body: multipart (--form NAME=VALUE, --file NAME=@PATH)")?;
+    } else if op.has_body {
+        write_stdout_line("  body: --body JSON, @file.json, or - (stdin)")?;
+        if op.is_merge_patch() {
+            write_stdout_line("  content type: application/merge-patch+json")?;
+        }
+        if op.method.eq_ignore_ascii_case("PATCH") {
+            write_stdout_line("  merge: --merge NAME=VALUE sends only the changed fields")?;
+        }
     }
     Ok(())
 }
 
 fn handle_tree(tree: &CommandTree, matches: &clap::ArgMatches) -> Result<()> {
-    if matches.get_flag("json") {
-        write_stdout_line(&serde_json::to_string_pretty(tree)?)?;
+    if let Some(format) = structured_format(matches)? {
+        let value = serde_json::to_value(tree)?;
+        write_stdout_line(&render::render_as(&value, format, true, &[])?)?;
         return Ok(());
     }
-    write_stdout_line("Run with --json for machine-readable output.")?;
+    write_stdout_line("Run with --json or --output yaml for machine-readable output.")?;
     Ok(())
 }
 
+/// `--json`, or an explicit `--output`, switches the introspection commands from their
+/// human-readable text to a structured format.
+fn structured_format(matches: &clap::ArgMatches) -> Result<Option<render::OutputFormat>> {
+    if matches.get_flag("json") {
+        return Ok(Some(render::OutputFormat::Json));
+    }
+    if matches.value_source("output") != Some(ValueSource::CommandLine) {
+        return Ok(None);
+    }
+    matches
+        .get_one::<String>("output")
+        .map(String::as_str)
+        .map(render::OutputFormat::from_name)
+        .transpose()
+}
+
+fn write_output_file(path: &Path, value: &str) -> Result<()> {
+    write_file_bytes(path, format!("{value}\n").as_bytes())
+}
+
+fn write_file_bytes(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+        && !parent.is_dir()
+    {
+        return Err(anyhow!(
+            "--output-file: directory {} does not exist",
+            parent.display()
+        ));
+    }
+    fs::write(path, bytes).with_context(|| format!("write {}", path.display()))
+}
+
+/// Last non-empty segment of a request path, as a fallback download name.
+fn path_file_name(path: &str) -> Option<String> {
+    path.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .map(str::to_string)
+}
+
 fn write_stdout_line(value: &str) -> Result<()> {
     let mut out = std::io::stdout().lock();
     if let Err(err) = out.write_all(value.as_bytes()) {
@@ -254,10 +2778,57 @@ fn write_stdout_line(value: &str) -> Result<()> {
 }
 
 fn build_param_arg(param: &ParamDef) -> Arg {
-    Arg::new(param.name.clone())
+    let mut arg = Arg::new(param.name.clone())
         .long(param.flag.clone())
-        .value_name("VALUE")
-        .required(param.required && param.location == "path")
+        .value_name("VALUE");
+    if param.multiple && param.location == "query" {
+        arg = arg.action(ArgAction::Append);
+    }
+    if let Some(values) = &param.enum_values {
+        arg = arg.value_parser(clap::builder::PossibleValuesParser::new(values.clone()));
+    } else if param.value_type() != "string" || param.pattern.is_some() {
+        if param.value_type() != "string" {
+            arg = arg.value_name(param.value_type().to_ascii_uppercase());
+        }
+        let def = param.clone();
+        arg = arg.value_parser(move |raw: &str| {
+            // `${capture}` references are checked once expanded.
+            if raw.contains("${") {
+                Ok(raw.to_string())
+            } else {
+                def.checked_value(raw)
+            }
+        });
+    }
+    if let Some(default) = &param.default
+        && param.location == "query"
+    {
+        arg = arg.default_value(default.clone());
+    }
+    let needs_value = param.location != "query" || param.default.is_none();
+    if param.required && needs_value {
+        // A param file or example may supply the value instead.
+        arg.required_unless_present_any(["param_file", "example_run", "interactive"])
+    } else {
+        arg
+    }
+}
+
+fn select_example(op: &Operation, index: usize) -> Result<&Value> {
+    if op.examples.is_empty() {
+        return Err(anyhow!("{} has no examples", op.name));
+    }
+    op.examples.get(index).ok_or_else(|| {
+        anyhow!(
+            "{} has {} example(s); index {index} is out of range",
+            op.name,
+            op.examples.len()
+        )
+    })
+}
+
+fn example_param(example: Option<&Value>, name: &str) -> Option<String> {
+    param_file::scalar_to_string(example?.get("params")?.get(name)?)
 }
 
 fn find_op<'a>(tree: &'a CommandTree, res: &str, op: &str) -> Option<&'a Operation> {
@@ -267,21 +2838,87 @@ fn find_op<'a>(tree: &'a CommandTree, res: &str, op: &str) -> Option<&'a Operati
         .and_then(|r| r.ops.iter().find(|o| o.name == op))
 }
 
+/// "unknown command" with up to three "did you mean" suggestions: ops of the resource when
+/// it exists, otherwise resource names.
+fn unknown_command(tree: &CommandTree, res: &str, op: &str) -> anyhow::Error {
+    let suggestions = match tree.resources.iter().find(|r| r.name == res) {
+        Some(resource) => suggest::closest(op, resource.ops.iter().map(|o| o.name.as_str()), 3)
+            .into_iter()
+            .map(|name| format!("{res} {name}"))
+            .collect::<Vec<_>>(),
+        None => suggest::closest(res, tree.resources.iter().map(|r| r.name.as_str()), 3)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+    };
+    if suggestions.is_empty() {
+        return anyhow!("unknown command {res} {op}");
+    }
+    let quoted: Vec<String> = suggestions.iter().map(|name| format!("`{name}`")).collect();
+    anyhow!(
+        "unknown command {res} {op}; did you mean {}?",
+        quoted.join(", ")
+    )
+}
+
 fn build_request_parts(
     op: &Operation,
-    matches: &clap::ArgMatches,
-) -> Result<(String, Vec<(String, String)>)> {
+    matches: Option<&clap::ArgMatches>,
+    captures: &capture::Captures,
+    params: Option<&param_file::ParamFile>,
+    example: Option<&Value>,
+    interactive: bool,
+) -> Result<(String, Vec<(String, String)>, Vec<(String, String)>)> {
     let mut path = op.path.clone();
     let mut query = Vec::new();
+    let mut headers = Vec::new();
 
     for param in &op.params {
-        let value = matches.get_one::<String>(&param.name).map(String::as_str);
+        if param.multiple
+            && param.location == "query"
+            && let Some(values) = matches.and_then(|matches| matches.get_many::<String>(&param.name))
+        {
+            for value in values {
+                query.push((
+                    param.name.clone(),
+                    checked_value(param, &captures.expand(value)?)?,
+                ));
+            }
+            continue;
+        }
+        // Clap fills in the schema default; it should only win over nothing at all.
+        let value = matches
+            .filter(|matches| matches.value_source(&param.name) != Some(ValueSource::DefaultValue))
+            .and_then(|matches| matches.get_one::<String>(&param.name))
+            .cloned()
+            .or_else(|| params.and_then(|params| params.get(&param.location, &param.name)))
+            .or_else(|| example_param(example, &param.name))
+            .or_else(|| param.default.clone().filter(|_| param.location == "query"))
+            .map(|value| captures.expand(&value))
+            .transpose()?;
+        let needed = param.location == "path" || param.required;
+        let value = match value {
+            None if needed && interactive => Some(prompt::param_value(param)?),
+            value => value,
+        };
+        // Param files, examples and captures bypass clap's parser.
+        let value = value.map(|value| checked_value(param, &value)).transpose()?;
         if param.location == "path" {
             let value = value.ok_or_else(|| anyhow!("missing required argument --{}", param.flag))?;
-            path = replace_path_param(&path, &param.name, value);
-        } else if param.location == "query" {
-            if let Some(value) = value {
-                query.push((param.name.clone(), value.to_string()));
+            path = replace_path_param(&path, &param.name, &value);
+        } else {
+            let target = if param.location == "query" {
+                &mut query
+            } else {
+                &mut headers
+            };
+            match value {
+                Some(value) => target.push((param.name.clone(), value)),
+                // Clap enforces this for flags; param files, examples and batch lines don't.
+                None if param.required => {
+                    return Err(anyhow!("missing required argument --{}", param.flag));
+                }
+                None => {}
             }
         }
     }
@@ -290,33 +2927,440 @@ fn build_request_parts(
         return Err(anyhow!("unresolved path params: {path}"));
     }
 
-    Ok((path, query))
+    Ok((path, query, headers))
+}
+
+fn checked_value(param: &ParamDef, raw: &str) -> Result<String> {
+    param
+        .checked_value(raw)
+        .map_err(|err| anyhow!("invalid value for --{}: {err}", param.flag))
 }
 
+/// RFC 3986 unreserved characters stay literal; everything else in a path value
+/// (`/`, spaces, unicode) is percent-encoded.
+const PATH_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
 fn replace_path_param(path: &str, name: &str, value: &str) -> String {
+    let encoded = utf8_percent_encode(value, PATH_VALUE).to_string();
     let mut out = path.to_string();
     for placeholder in [
         format!("{{{name}}}"),
         format!(":{name}"),
         format!("{{{{{name}}}}}"),
     ] {
-        out = out.replace(&placeholder, value);
+        out = out.replace(&placeholder, &encoded);
     }
     out
 }
 
+/// `--form name=value` text parts, then `--file name=@path` file parts.
+fn parse_form_args(
+    matches: &clap::ArgMatches,
+    captures: &capture::Captures,
+) -> Result<Vec<http::FormPart>> {
+    let mut parts = Vec::new();
+    for spec in matches.get_many::<String>("form").into_iter().flatten() {
+        let (name, value) = spec
+            .split_once('=')
+            .filter(|(name, _)| !name.is_empty())
+            .ok_or_else(|| anyhow!("invalid --form {spec:?} (expected name=value)"))?;
+        parts.push(http::FormPart::Text {
+            name: name.to_string(),
+            value: captures.expand(value)?,
+        });
+    }
+    for spec in matches.get_many::<String>("file").into_iter().flatten() {
+        let (name, path) = spec
+            .split_once('=')
+            .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+            .ok_or_else(|| anyhow!("invalid --file {spec:?} (expected name=@path)"))?;
+        let path = PathBuf::from(path.strip_prefix('@').unwrap_or(path));
+        if !path.is_file() {
+            return Err(anyhow!("--file {name}: {} is not a file", path.display()));
+        }
+        parts.push(http::FormPart::File {
+            name: name.to_string(),
+            path,
+        });
+    }
+    Ok(parts)
+}
+
 fn parse_body_arg(matches: &clap::ArgMatches) -> Result<Option<Value>> {
-    let Some(value) = matches.get_one::<String>("body") else {
-        return Ok(None);
+    let raw = match matches.get_one::<String>("body") {
+        Some(value) => Some(read_body_source(value)?),
+        None => None,
     };
 
-    let raw = if let Some(path) = value.strip_prefix('@') {
-        let body_path = Path::new(path);
-        fs::read_to_string(body_path).context("read body file")?
+    let mut body = if matches.get_flag("open_in_editor") {
+        let initial = match raw.as_deref().map(serde_json::from_str::<Value>) {
+            Some(Ok(value)) => serde_json::to_string_pretty(&value)?,
+            Some(Err(_)) => raw.unwrap_or_default(),
+            None => "{\n}\n".to_string(),
+        };
+        Some(editor::edit_json(&initial)?)
+    } else if let Some(raw) = raw {
+        Some(serde_json::from_str(&raw).context("invalid JSON body")?)
     } else {
-        value.to_string()
+        None
+    };
+
+    for spec in matches.get_many::<String>("field").into_iter().flatten() {
+        let (name, value) = parse_field_arg("--field", spec)?;
+        insert_body_field(
+            body.get_or_insert_with(|| Value::Object(Default::default())),
+            name,
+            value,
+        )?;
+    }
+
+    let merges = matches.try_get_many::<String>("merge").ok().flatten();
+    for spec in merges.into_iter().flatten() {
+        let (name, value) = parse_field_arg("--merge", spec)?;
+        insert_body_field(
+            body.get_or_insert_with(|| Value::Object(Default::default())),
+            name,
+            value,
+        )?;
+    }
+
+    for spec in matches.get_many::<String>("field_file").into_iter().flatten() {
+        let (name, path) = spec
+            .split_once('=')
+            .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+            .ok_or_else(|| anyhow!("invalid --field-file {spec:?} (expected name=@path)"))?;
+        let path = path.strip_prefix('@').unwrap_or(path);
+        let bytes = fs::read(path).with_context(|| format!("read {path}"))?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+        insert_body_field(
+            body.get_or_insert_with(|| Value::Object(Default::default())),
+            name,
+            Value::String(encoded),
+        )?;
+    }
+
+    // Only ops whose body declares external_id define --auto-external-id.
+    if let Some(prefix) = matches
+        .try_get_one::<String>("auto_external_id")
+        .ok()
+        .flatten()
+    {
+        let body = body.get_or_insert_with(|| Value::Object(Default::default()));
+        let map = body
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("--auto-external-id needs a JSON object body"))?;
+        if !map.contains_key("external_id") {
+            let external_id = format!("{prefix}{}", uuid::Uuid::new_v4().simple());
+            eprintln!("external_id: {external_id}");
+            map.insert("external_id".to_string(), Value::String(external_id));
+        }
+    }
+
+    Ok(body)
+}
+
+/// Parses `name=value` (string) or `name:=json` (raw JSON), httpie-style.
+fn parse_field_arg<'a>(flag: &str, spec: &'a str) -> Result<(&'a str, Value)> {
+    let (name, value) = spec
+        .split_once('=')
+        .ok_or_else(|| anyhow!("invalid {flag} {spec:?} (expected name=value or name:=json)"))?;
+    let (name, value) = match name.strip_suffix(':') {
+        Some(name) => {
+            let value = serde_json::from_str(value)
+                .with_context(|| format!("invalid JSON in {flag} {spec:?}"))?;
+            (name, value)
+        }
+        None => (name, Value::String(value.to_string())),
     };
+    if name.is_empty() {
+        return Err(anyhow!("invalid {flag} {spec:?}: empty name"));
+    }
+    Ok((name, value))
+}
+
+/// Sets `name` (a dotted path such as `customer.email`) in a JSON object body.
+fn insert_body_field(body: &mut Value, name: &str, value: Value) -> Result<()> {
+    let mut current = body;
+    let mut segments = name.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let map = current
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("cannot set field {name}: body is not a JSON object"))?;
+        if segments.peek().is_none() {
+            map.insert(segment.to_string(), value);
+            return Ok(());
+        }
+        current = map
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Default::default()));
+    }
+    Ok(())
+}
+
+fn read_body_source(value: &str) -> Result<String> {
+    if value == "-" {
+        let mut raw = String::new();
+        std::io::stdin()
+            .read_to_string(&mut raw)
+            .context("read body from stdin")?;
+        if raw.trim().is_empty() {
+            return Err(anyhow!(
+                "--body - got empty stdin; pipe a JSON body, e.g. `cat body.json | xendit ... --body -`"
+            ));
+        }
+        return Ok(raw);
+    }
+    if let Some(path) = value.strip_prefix('@') {
+        let body_path = Path::new(path);
+        return fs::read_to_string(body_path).context("read body file");
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{Reply, TestServer};
+
+    fn test_tree() -> CommandTree {
+        serde_json::from_value(serde_json::json!({
+            "version": 1,
+            "base_url": "https://api.xendit.co",
+            "resources": [{
+                "name": "payouts",
+                "ops": [
+                    {
+                        "name": "list-payouts", "method": "GET", "path": "/v2/payouts",
+                        "has_body": false,
+                        "params": [
+                            {"name": "statuses", "flag": "statuses", "location": "query",
+                             "required": false, "multiple": true},
+                            {"name": "currency", "flag": "currency", "location": "query",
+                             "required": true},
+                            {"name": "limit", "flag": "limit", "location": "query",
+                             "required": false},
+                        ],
+                    },
+                    {
+                        "name": "get-payout", "method": "GET", "path": "/v2/payouts/{id}",
+                        "has_body": false,
+                        "params": [
+                            {"name": "id", "flag": "id", "location": "path", "required": true},
+                            {"name": "X-Trace-Id", "flag": "trace-id", "location": "header",
+                             "required": false},
+                        ],
+                    },
+                    {
+                        "name": "create-payout", "method": "POST", "path": "/v2/payouts",
+                        "has_body": true, "external_id": true, "params": [],
+                    },
+                    {
+                        "name": "update-payout", "method": "PATCH", "path": "/v2/payouts/{id}",
+                        "has_body": true,
+                        "params": [
+                            {"name": "id", "flag": "id", "location": "path", "required": true},
+                        ],
+                    },
+                ],
+            }],
+        }))
+        .unwrap()
+    }
+
+    fn parse(tree: &CommandTree, args: &[&str]) -> Result<clap::ArgMatches, clap::Error> {
+        build_cli(tree).try_get_matches_from(std::iter::once("xendit").chain(args.iter().copied()))
+    }
+
+    type Parts = (String, Vec<(String, String)>, Vec<(String, String)>);
 
-    let parsed = serde_json::from_str(&raw).context("invalid JSON body")?;
-    Ok(Some(parsed))
+    /// `build_request_parts` for `payouts <op>` parsed from `args`.
+    fn request_parts(tree: &CommandTree, args: &[&str]) -> Parts {
+        let matches = parse(tree, args).unwrap();
+        let (_, op_matches) = matches
+            .subcommand_matches("payouts")
+            .and_then(clap::ArgMatches::subcommand)
+            .unwrap();
+        let op = find_op(tree, "payouts", args[1]).unwrap();
+        build_request_parts(
+            op,
+            Some(op_matches),
+            &capture::Captures::default(),
+            None,
+            None,
+            false,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn repeated_query_values_are_all_sent() {
+        let tree = test_tree();
+        let (path, query, _) = request_parts(
+            &tree,
+            &[
+                "payouts",
+                "list-payouts",
+                "--statuses",
+                "PENDING",
+                "--statuses",
+                "PAID",
+                "--currency",
+                "IDR",
+            ],
+        );
+        let server = TestServer::start(|_| Reply::json(200, r#"{"data": []}"#));
+        let client = http::HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &http::ClientOptions::default(),
+        )
+        .unwrap();
+        let op = find_op(&tree, "payouts", "list-payouts").unwrap();
+        client.execute(op, &path, &query, &[], None).unwrap();
+
+        let target = &server.requests()[0].target;
+        assert_eq!(
+            target,
+            "/v2/payouts?statuses=PENDING&statuses=PAID&currency=IDR"
+        );
+    }
+
+    #[test]
+    fn path_values_are_encoded_as_one_segment() {
+        assert_eq!(
+            replace_path_param("/v2/payouts/{id}", "id", "foo/bar baz"),
+            "/v2/payouts/foo%2Fbar%20baz"
+        );
+        assert_eq!(
+            replace_path_param("/v2/payouts/:id/cancel", "id", "ö-1_a.b~"),
+            "/v2/payouts/%C3%B6-1_a.b~/cancel"
+        );
+        let (path, _, _) = request_parts(
+            &test_tree(),
+            &["payouts", "get-payout", "--id", "foo/bar baz"],
+        );
+        assert_eq!(path, "/v2/payouts/foo%2Fbar%20baz");
+    }
+
+    #[test]
+    fn header_params_become_request_headers() {
+        let args = ["payouts", "get-payout", "--id", "po-1", "--trace-id", "t-1"];
+        let (path, query, headers) = request_parts(&test_tree(), &args);
+        assert_eq!(path, "/v2/payouts/po-1");
+        assert!(query.is_empty());
+        assert_eq!(headers, [("X-Trace-Id".to_string(), "t-1".to_string())]);
+    }
+
+    #[test]
+    fn a_missing_required_query_param_is_a_usage_error() {
+        let tree = test_tree();
+        let err = parse(&tree, &["payouts", "list-payouts", "--limit", "10"]).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+        assert_eq!(err.exit_code(), 2);
+        assert!(err.to_string().contains("--currency"), "{err}");
+
+        // Optional query params stay optional.
+        let (_, query, _) = request_parts(&tree, &["payouts", "list-payouts", "--currency", "IDR"]);
+        assert_eq!(query, [("currency".to_string(), "IDR".to_string())]);
+    }
+
+    #[test]
+    fn auto_external_id_is_only_offered_where_the_body_declares_it() {
+        let tree = test_tree();
+        let body_of = |matches: &clap::ArgMatches| {
+            let (_, op_matches) = matches
+                .subcommand_matches("payouts")
+                .and_then(clap::ArgMatches::subcommand)
+                .unwrap();
+            parse_body_arg(op_matches).unwrap().unwrap()
+        };
+
+        let matches = parse(
+            &tree,
+            &[
+                "payouts",
+                "create-payout",
+                "--field",
+                "amount:=1",
+                "--auto-external-id=po-",
+            ],
+        )
+        .unwrap();
+        let body = body_of(&matches);
+        assert!(
+            body["external_id"].as_str().unwrap().starts_with("po-"),
+            "{body}"
+        );
+
+        let err = parse(
+            &tree,
+            &[
+                "payouts",
+                "update-payout",
+                "--id",
+                "po-1",
+                "--auto-external-id",
+            ],
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::UnknownArgument);
+        let matches = parse(
+            &tree,
+            &[
+                "payouts",
+                "update-payout",
+                "--id",
+                "po-1",
+                "--field",
+                "amount:=1",
+            ],
+        )
+        .unwrap();
+        assert_eq!(body_of(&matches), serde_json::json!({"amount": 1}));
+    }
+
+    #[test]
+    fn a_xendit_400_surfaces_its_error_code_and_exits_22() {
+        let fixture = include_str!("../tests/fixtures/error_400.json");
+        let server = TestServer::start(move |_| Reply::json(400, fixture));
+        let client = http::HttpClient::new(
+            server.base_url.clone(),
+            "key".into(),
+            &http::ClientOptions::default(),
+        )
+        .unwrap();
+        let op: Operation = serde_json::from_value(serde_json::json!({
+            "name": "create-invoice", "method": "POST", "path": "/v2/invoices", "params": [],
+            "has_body": true,
+        }))
+        .unwrap();
+        let resp = client
+            .execute(
+                &op,
+                "/v2/invoices",
+                &[],
+                &[],
+                Some(http::Body::Json(serde_json::json!({"amount": 0}))),
+            )
+            .unwrap();
+        // The body stays intact for stdout; the error carries the parsed fields.
+        assert_eq!(resp.body, serde_json::from_str::<Value>(fixture).unwrap());
+
+        let err: anyhow::Error = http::StatusError::from_response(&resp).into();
+        let status = err.downcast_ref::<http::StatusError>().unwrap();
+        assert_eq!(status.code.as_deref(), Some("API_VALIDATION_ERROR"));
+        assert_eq!(
+            status.message.as_deref(),
+            Some("amount must be a number greater than or equal to 1")
+        );
+        assert_eq!(
+            err.to_string(),
+            "http 400: API_VALIDATION_ERROR: amount must be a number greater than or equal to 1"
+        );
+        assert_eq!(exit_code(&err), 22);
+    }
 }
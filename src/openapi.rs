@@ -0,0 +1,441 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::command_tree::{CommandTree, MERGE_PATCH, Operation, ParamDef, Resource, VALUE_TYPES};
+use crate::param_file;
+
+const METHODS: [&str; 7] = ["get", "post", "put", "patch", "delete", "head", "options"];
+/// Keys of a path item that aren't operations.
+const PATH_ITEM_KEYS: [&str; 5] = ["parameters", "summary", "description", "servers", "$ref"];
+const MULTIPART: &str = "multipart/form-data";
+
+/// Builds a command tree from an OpenAPI 3 document (JSON or YAML), mirroring
+/// `tools/gen_command_tree.py`: resources from the first tag (else the first path
+/// segment), ops from `operationId` (else method + path), params from path/query/header
+/// parameters, `has_body` from `requestBody`, `external_id` from a JSON body schema
+/// with that property. Unsupported constructs are skipped with a warning.
+pub fn generate(spec_path: &Path) -> Result<CommandTree> {
+    let raw =
+        fs::read_to_string(spec_path).with_context(|| format!("read {}", spec_path.display()))?;
+    // YAML is a superset of JSON, so one parser covers both.
+    let spec: Value = serde_yaml::from_str(&raw)
+        .with_context(|| format!("invalid OpenAPI document {}", spec_path.display()))?;
+    Ok(from_spec(&spec))
+}
+
+fn from_spec(spec: &Value) -> CommandTree {
+    let mut resources: BTreeMap<String, Vec<Operation>> = BTreeMap::new();
+    let mut seen: BTreeMap<String, HashSet<String>> = BTreeMap::new();
+    // An op's tags after the first become tags on its resource.
+    let mut resource_tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    let paths = spec.get("paths").and_then(Value::as_object);
+    for (path, item) in paths.into_iter().flatten() {
+        let Some(item) = item.as_object() else {
+            eprintln!("warning: skipping {path}: path item is not an object");
+            continue;
+        };
+        let shared = item.get("parameters");
+        for (method, details) in item {
+            if PATH_ITEM_KEYS.contains(&method.as_str()) {
+                continue;
+            }
+            if !METHODS.contains(&method.to_ascii_lowercase().as_str()) {
+                eprintln!("warning: skipping unsupported method {method} {path}");
+                continue;
+            }
+
+            let tags = details.get("tags").and_then(Value::as_array);
+            let resource = match tags.and_then(|tags| tags.first()).and_then(Value::as_str) {
+                Some(tag) => camel_to_kebab(tag),
+                None => camel_to_kebab(path.split('/').nth(1).unwrap_or("root")),
+            };
+            let resource = if resource.is_empty() {
+                "root".to_string()
+            } else {
+                resource
+            };
+            let extra_tags = tags.into_iter().flatten().skip(1).filter_map(Value::as_str);
+            let own_tags = resource_tags.entry(resource.clone()).or_default();
+            for tag in extra_tags.map(camel_to_kebab) {
+                if !tag.is_empty() && !own_tags.contains(&tag) {
+                    own_tags.push(tag);
+                }
+            }
+            let base_name = match details.get("operationId").and_then(Value::as_str) {
+                Some(id) => normalize_op_name(id),
+                None => normalize_op_name(&format!("{method}-{path}")),
+            };
+            let used = seen.entry(resource.clone()).or_default();
+            let name = dedupe(used, &base_name, method);
+
+            let params = shared
+                .into_iter()
+                .chain(details.get("parameters"))
+                .filter_map(Value::as_array)
+                .flatten()
+                .filter_map(|param| convert_param(param, method, path))
+                .fold(Vec::<ParamDef>::new(), |mut acc, param| {
+                    // Operation-level params override path-level ones with the same name.
+                    acc.retain(|p| !(p.name == param.name && p.location == param.location));
+                    acc.push(param);
+                    acc
+                });
+
+            let description = details
+                .get("summary")
+                .or_else(|| details.get("description"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            let content = details
+                .get("requestBody")
+                .and_then(|body| body.get("content"));
+            let has_content = |content_type: &str| {
+                content
+                    .and_then(|content| content.get(content_type))
+                    .is_some()
+            };
+            resources.entry(resource).or_default().push(Operation {
+                name,
+                method: method.to_ascii_uppercase(),
+                path: path.clone(),
+                description,
+                params,
+                has_body: details.get("requestBody").is_some(),
+                body_kind: has_content(MULTIPART).then(|| "multipart".to_string()),
+                body_content_type: (has_content(MERGE_PATCH) && !has_content("application/json"))
+                    .then(|| MERGE_PATCH.to_string()),
+                rate_limit: None,
+                deprecated: (details.get("deprecated").and_then(Value::as_bool) == Some(true))
+                    .then(String::new),
+                examples: Vec::new(),
+                external_id: declares_external_id(spec, content),
+            });
+        }
+    }
+
+    let base_url = spec
+        .get("servers")
+        .and_then(Value::as_array)
+        .and_then(|servers| servers.first())
+        .and_then(|server| server.get("url"))
+        .and_then(Value::as_str)
+        .unwrap_or("https://api.xendit.co")
+        .to_string();
+
+    CommandTree {
+        version: 1,
+        base_url,
+        version_header: None,
+        resources: resources
+            .into_iter()
+            .map(|(name, ops)| Resource {
+                tags: resource_tags.remove(&name).unwrap_or_default(),
+                name,
+                ops,
+            })
+            .collect(),
+    }
+}
+
+/// The inverse of `generate`: a minimal OpenAPI 3 document with a tag per resource, an
+/// `operationId` per op (prefixed with the resource when names collide across
+/// resources), path/query/header parameters and a placeholder `requestBody` (declaring
+/// `external_id` where the op has it). Examples and rate limits are not exported.
+pub fn export(tree: &CommandTree) -> Value {
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for op in tree.resources.iter().flat_map(|res| &res.ops) {
+        *name_counts.entry(op.name.as_str()).or_default() += 1;
+    }
+
+    let mut paths: BTreeMap<&str, Map<String, Value>> = BTreeMap::new();
+    for res in &tree.resources {
+        for op in &res.ops {
+            let method = op.method.to_ascii_lowercase();
+            let item = paths.entry(op.path.as_str()).or_default();
+            if item.contains_key(&method) {
+                eprintln!(
+                    "warning: skipping {} {}: {} {} is already exported",
+                    res.name, op.name, op.method, op.path
+                );
+                continue;
+            }
+            let operation_id = if name_counts[op.name.as_str()] > 1 {
+                format!("{}-{}", res.name, op.name)
+            } else {
+                op.name.clone()
+            };
+            let mut operation = json!({
+                "operationId": operation_id,
+                "tags": std::iter::once(&res.name).chain(&res.tags).collect::<Vec<_>>(),
+            });
+            if let Some(desc) = op.description.as_deref().map(str::trim)
+                && !desc.is_empty()
+            {
+                operation["summary"] = json!(desc);
+            }
+            if op.deprecated.is_some() {
+                operation["deprecated"] = json!(true);
+            }
+            if !op.params.is_empty() {
+                operation["parameters"] = op.params.iter().map(export_param).collect();
+            }
+            if op.has_body {
+                let content_type = if op.is_multipart() {
+                    MULTIPART
+                } else if op.is_merge_patch() {
+                    MERGE_PATCH
+                } else {
+                    "application/json"
+                };
+                let mut schema = json!({"type": "object"});
+                if op.external_id {
+                    schema["properties"] = json!({"external_id": {"type": "string"}});
+                }
+                operation["requestBody"] = json!({
+                    "required": true,
+                    "content": {content_type: {"schema": schema}},
+                });
+            }
+            operation["responses"] = json!({"default": {"description": "Response"}});
+            item.insert(method, operation);
+        }
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {"title": "Xendit API", "version": tree.version.to_string()},
+        "servers": [{"url": tree.base_url}],
+        "paths": paths,
+    })
+}
+
+fn export_param(param: &ParamDef) -> Value {
+    // `value_type` names are OpenAPI's own scalar types.
+    let mut schema = json!({"type": param.value_type()});
+    if let Some(values) = &param.enum_values {
+        schema["enum"] = json!(values);
+    }
+    if let Some(pattern) = &param.pattern {
+        schema["pattern"] = json!(pattern);
+    }
+    if param.multiple {
+        schema = json!({"type": "array", "items": schema});
+    }
+    // Kept as a plain string (even for arrays) so `generate` reads it back.
+    if let Some(default) = &param.default {
+        schema["default"] = json!(default);
+    }
+    json!({
+        "name": param.name,
+        "in": param.location,
+        "required": param.required,
+        "schema": schema,
+    })
+}
+
+fn convert_param(param: &Value, method: &str, path: &str) -> Option<ParamDef> {
+    if let Some(reference) = param.get("$ref").and_then(Value::as_str) {
+        eprintln!("warning: {method} {path}: skipping $ref parameter {reference}");
+        return None;
+    }
+    let name = param.get("name").and_then(Value::as_str)?;
+    let location = param.get("in").and_then(Value::as_str)?;
+    if !["path", "query", "header"].contains(&location) {
+        eprintln!("warning: {method} {path}: skipping {location} parameter {name}");
+        return None;
+    }
+    let required = param
+        .get("required")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let pattern = param
+        .pointer("/schema/items/pattern")
+        .or_else(|| param.pointer("/schema/pattern"))
+        .and_then(Value::as_str);
+    let pattern = match pattern {
+        // ECMA-262 lookarounds and backreferences have no `regex` equivalent.
+        Some(pattern) if Regex::new(pattern).is_err() => {
+            eprintln!("warning: {method} {path}: ignoring unsupported pattern on {name}");
+            None
+        }
+        pattern => pattern.map(str::to_string),
+    };
+    Some(ParamDef {
+        name: name.to_string(),
+        flag: camel_to_kebab(name),
+        location: location.to_string(),
+        required: required || location == "path",
+        // `style: form` with `explode` (the default) repeats the key for arrays.
+        multiple: location == "query"
+            && param.pointer("/schema/type").and_then(Value::as_str) == Some("array"),
+        enum_values: param
+            .pointer("/schema/enum")
+            .or_else(|| param.pointer("/schema/items/enum"))
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(param_file::scalar_to_string)
+                    .collect()
+            }),
+        default: if location == "query" {
+            param
+                .pointer("/schema/default")
+                .and_then(param_file::scalar_to_string)
+        } else {
+            None
+        },
+        value_type: param
+            .pointer("/schema/items/type")
+            .or_else(|| param.pointer("/schema/type"))
+            .and_then(Value::as_str)
+            .filter(|kind| *kind != "string" && VALUE_TYPES.contains(kind))
+            .map(str::to_string),
+        pattern,
+    })
+}
+
+/// Whether the JSON body schema has an `external_id` property, following one local
+/// `$ref` into `components`.
+fn declares_external_id(spec: &Value, content: Option<&Value>) -> bool {
+    let Some(schema) = content
+        .and_then(|content| {
+            content
+                .get("application/json")
+                .or_else(|| content.get(MERGE_PATCH))
+        })
+        .and_then(|media| media.get("schema"))
+    else {
+        return false;
+    };
+    let schema = match schema.get("$ref").and_then(Value::as_str) {
+        Some(reference) => match reference.strip_prefix('#') {
+            Some(pointer) => spec.pointer(pointer).unwrap_or(&Value::Null),
+            None => &Value::Null,
+        },
+        None => schema,
+    };
+    schema.pointer("/properties/external_id").is_some()
+}
+
+fn dedupe(used: &mut HashSet<String>, name: &str, method: &str) -> String {
+    let mut candidate = name.to_string();
+    if used.contains(&candidate) {
+        candidate = normalize_op_name(&format!("{name}-{method}"));
+    }
+    let mut idx = 2;
+    while used.contains(&candidate) {
+        candidate = normalize_op_name(&format!("{name}-{idx}"));
+        idx += 1;
+    }
+    used.insert(candidate.clone());
+    candidate
+}
+
+fn normalize_op_name(value: &str) -> String {
+    let name = camel_to_kebab(value);
+    if name.is_empty() {
+        "call".to_string()
+    } else {
+        name
+    }
+}
+
+/// `createPaymentRequest` / `payment_requests` / `v2/accounts` -> kebab-case.
+fn camel_to_kebab(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 8);
+    let mut prev: Option<char> = None;
+    for c in value.chars() {
+        let c = if matches!(c, '/' | '_' | ' ') { '-' } else { c };
+        if c.is_ascii_uppercase()
+            && prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+        {
+            out.push('-');
+        }
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.ends_with('-') {
+            out.push('-');
+        }
+        prev = Some(c);
+    }
+    out.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> CommandTree {
+        serde_json::from_value(json!({
+            "version": 1,
+            "base_url": "https://api.xendit.co",
+            "resources": [
+                {"name": "invoices", "tags": ["payments"], "ops": [
+                    {"name": "list-invoices", "method": "GET", "path": "/v2/invoices",
+                     "description": "List invoices", "has_body": false, "params": [
+                         {"name": "limit", "flag": "limit", "location": "query",
+                          "required": false, "value_type": "integer", "default": "10"},
+                         {"name": "statuses", "flag": "statuses", "location": "query",
+                          "required": false, "multiple": true, "enum_values": ["PAID", "PENDING"]},
+                         {"name": "for-user-id", "flag": "for-user-id", "location": "header",
+                          "required": false},
+                     ]},
+                    {"name": "create-invoice", "method": "POST", "path": "/v2/invoices",
+                     "has_body": true, "external_id": true, "params": [
+                         {"name": "idempotency-key", "flag": "idempotency-key",
+                          "location": "header", "required": true},
+                     ]},
+                    {"name": "update-invoice", "method": "PATCH", "path": "/v2/invoices/{id}",
+                     "has_body": true, "body_content_type": MERGE_PATCH, "deprecated": "",
+                     "params": [
+                         {"name": "id", "flag": "id", "location": "path", "required": true},
+                     ]},
+                ]},
+                {"name": "uploads", "ops": [
+                    {"name": "upload-file", "method": "POST", "path": "/files", "params": [],
+                     "has_body": true, "body_kind": "multipart"},
+                ]},
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn export_then_generate_round_trips() {
+        let tree = tree();
+        let regenerated = from_spec(&export(&tree));
+        assert_eq!(
+            serde_json::to_value(&regenerated).unwrap(),
+            serde_json::to_value(&tree).unwrap()
+        );
+    }
+
+    #[test]
+    fn header_params_are_kept() {
+        let spec = json!({"paths": {"/v2/invoices": {"get": {
+            "operationId": "listInvoices",
+            "parameters": [
+                {"name": "for-user-id", "in": "header", "schema": {"type": "string"}},
+                {"name": "session", "in": "cookie", "schema": {"type": "string"}},
+            ],
+        }}}});
+        let tree = from_spec(&spec);
+        let params = &tree.resources[0].ops[0].params;
+        assert_eq!(params.len(), 1);
+        assert_eq!(
+            (
+                params[0].flag.as_str(),
+                params[0].location.as_str(),
+                params[0].required
+            ),
+            ("for-user-id", "header", false)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use reqwest::Url;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::command_tree::Operation;
+use crate::http::{HttpClient, HttpResponse};
+
+pub struct PageOptions {
+    /// Query param that carries the cursor (`after_id` for most Xendit lists).
+    pub cursor_param: String,
+    pub max_pages: usize,
+}
+
+/// Follows Xendit's `has_more`/`links` pagination and merges every page's `data` array
+/// into a single response. Non-paginated bodies are returned unchanged. The merged
+/// response's `elapsed` covers every page.
+pub fn fetch_all(
+    client: &HttpClient,
+    op: &Operation,
+    path: &str,
+    query: &[(String, String)],
+    headers: &[(String, String)],
+    opts: &PageOptions,
+) -> Result<HttpResponse> {
+    let mut query = query.to_vec();
+    let mut items: Vec<Value> = Vec::new();
+    let mut pages = 0;
+    let mut elapsed = Duration::ZERO;
+
+    loop {
+        let mut resp = client.execute(op, path, &query, headers, None)?;
+        pages += 1;
+        elapsed += resp.elapsed;
+        resp.elapsed = elapsed;
+        if !resp.is_success() {
+            return Ok(resp);
+        }
+        let Some(page_items) = resp.body.get("data").and_then(Value::as_array) else {
+            return Ok(resp);
+        };
+        let page_len = page_items.len();
+        items.extend(page_items.iter().cloned());
+
+        let has_more = resp
+            .body
+            .get("has_more")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let cursor = next_cursor(&resp.body, &opts.cursor_param);
+        let more = has_more && page_len > 0 && cursor.is_some();
+
+        if !more || pages >= opts.max_pages {
+            if more {
+                eprintln!("pagination stopped after {pages} pages (--max-pages)");
+            }
+            if let Value::Object(map) = &mut resp.body {
+                map.insert("data".to_string(), Value::Array(items));
+                map.insert("has_more".to_string(), Value::Bool(more));
+                map.remove("links");
+            }
+            return Ok(resp);
+        }
+
+        if let Some(cursor) = cursor {
+            query.retain(|(name, _)| name != &opts.cursor_param);
+            query.push((opts.cursor_param.clone(), cursor));
+        }
+    }
+}
+
+/// Prefers the cursor from a `links` entry with `rel: next`, falling back to the last
+/// item's `id`.
+fn next_cursor(body: &Value, cursor_param: &str) -> Option<String> {
+    let from_links = body
+        .get("links")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|link| link.get("rel").and_then(Value::as_str) == Some("next"))
+        .filter_map(|link| link.get("href").and_then(Value::as_str))
+        .find_map(|href| cursor_from_href(href, cursor_param));
+    from_links.or_else(|| {
+        body.get("data")
+            .and_then(Value::as_array)
+            .and_then(|items| items.last())
+            .and_then(|item| item.get("id"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    })
+}
+
+fn cursor_from_href(href: &str, cursor_param: &str) -> Option<String> {
+    // Links may be relative (`/v2/payouts?after_id=...`).
+    let base = Url::parse("https://api.xendit.co").ok()?;
+    let url = base.join(href).ok()?;
+    url.query_pairs()
+        .find(|(name, _)| name == cursor_param)
+        .map(|(_, value)| value.into_owned())
+}
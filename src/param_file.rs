@@ -0,0 +1,113 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::command_tree::Operation;
+
+pub const FORMATS: [&str; 3] = ["auto", "flat", "scoped"];
+
+/// Parameter values loaded from `--param-file`, keyed by (location, name).
+pub struct ParamFile {
+    values: HashMap<(String, String), String>,
+}
+
+impl ParamFile {
+    /// Loads a flat `{name: value}` map or a scoped `{path: {...}, query: {...}}` map.
+    /// `auto` picks scoped when the only top-level keys are `path`/`query` objects.
+    pub fn load(path: &str, format: &str, op: &Operation) -> Result<Self> {
+        let raw = fs::read_to_string(path).with_context(|| format!("read param file {path}"))?;
+        let value: Value = serde_json::from_str(&raw)
+            .with_context(|| format!("invalid JSON in param file {path}"))?;
+        let map = value
+            .as_object()
+            .ok_or_else(|| anyhow!("param file {path} must contain a JSON object"))?;
+
+        let scoped = match format {
+            "flat" => false,
+            "scoped" => true,
+            _ => {
+                !map.is_empty()
+                    && map.iter().all(|(key, value)| {
+                        matches!(key.as_str(), "path" | "query") && value.is_object()
+                    })
+            }
+        };
+
+        let mut values = HashMap::new();
+        if scoped {
+            for (location, entries) in map {
+                let entries = entries
+                    .as_object()
+                    .filter(|_| matches!(location.as_str(), "path" | "query"))
+                    .ok_or_else(|| {
+                        anyhow!("scoped param file {path}: expected `path`/`query` objects, got `{location}`")
+                    })?;
+                for (name, value) in entries {
+                    if !op
+                        .params
+                        .iter()
+                        .any(|param| param.location == *location && param.name == *name)
+                    {
+                        return Err(anyhow!("{} has no {location} param {name}", op.name));
+                    }
+                    insert(&mut values, location, name, value);
+                }
+            }
+        } else {
+            load_flat(map, op, &mut values)?;
+        }
+        Ok(Self { values })
+    }
+
+    pub fn get(&self, location: &str, name: &str) -> Option<String> {
+        self.values
+            .get(&(location.to_string(), name.to_string()))
+            .cloned()
+    }
+}
+
+fn load_flat(
+    map: &Map<String, Value>,
+    op: &Operation,
+    values: &mut HashMap<(String, String), String>,
+) -> Result<()> {
+    for (name, value) in map {
+        let mut locations = op
+            .params
+            .iter()
+            .filter(|param| param.name == *name)
+            .map(|param| param.location.as_str());
+        let location = locations
+            .next()
+            .ok_or_else(|| anyhow!("{} has no param {name}", op.name))?;
+        if locations.next().is_some() {
+            return Err(anyhow!(
+                "param {name} is ambiguous for {}; use --param-file-format scoped",
+                op.name
+            ));
+        }
+        insert(values, location, name, value);
+    }
+    Ok(())
+}
+
+fn insert(
+    values: &mut HashMap<(String, String), String>,
+    location: &str,
+    name: &str,
+    value: &Value,
+) {
+    if let Some(value) = scalar_to_string(value) {
+        values.insert((location.to_string(), name.to_string()), value);
+    }
+}
+
+/// Renders a JSON scalar as a parameter value (strings unquoted, null skipped).
+pub fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::{Result, anyhow};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::command_tree::Operation;
+use crate::http::{HttpClient, HttpResponse};
+use crate::select::Condition;
+
+pub struct PollOptions {
+    pub until: Condition,
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Skips the per-poll progress lines (`--quiet`).
+    pub quiet: bool,
+}
+
+/// Re-sends `op` every `interval` until `until` holds for the body and returns that
+/// response. A non-2xx response ends polling early so the caller reports it as usual.
+pub fn poll_until(
+    client: &HttpClient,
+    op: &Operation,
+    path: &str,
+    query: &[(String, String)],
+    headers: &[(String, String)],
+    opts: &PollOptions,
+) -> Result<HttpResponse> {
+    let started = Instant::now();
+    let mut polls = 0;
+    loop {
+        let resp = client.execute(op, path, query, headers, None)?;
+        polls += 1;
+        if !resp.is_success() || opts.until.matches(&resp.body) {
+            return Ok(resp);
+        }
+        let elapsed = started.elapsed();
+        if !opts.quiet {
+            eprintln!(
+                "poll {polls}: {} (waiting for {}, {}s elapsed)",
+                opts.until.observed(&resp.body),
+                opts.until.expr(),
+                elapsed.as_secs()
+            );
+        }
+        if elapsed + opts.interval > opts.timeout {
+            return Err(anyhow!(
+                "condition {} not met after {polls} polls ({}s timeout)",
+                opts.until.expr(),
+                opts.timeout.as_secs_f64()
+            ));
+        }
+        thread::sleep(opts.interval);
+    }
+}
@@ -0,0 +1,49 @@
+use indicatif::ProgressBar;
+use std::io::IsTerminal;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Stderr spinner that only appears once a call has been running for `delay`,
+/// so fast calls never flicker. Cleared on drop.
+pub struct Spinner {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    pub fn start(message: String, delay: Duration, enabled: bool) -> Self {
+        if !enabled || !std::io::stderr().is_terminal() {
+            return Self {
+                stop: None,
+                handle: None,
+            };
+        }
+
+        let (stop, rx) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            if !matches!(rx.recv_timeout(delay), Err(RecvTimeoutError::Timeout)) {
+                return;
+            }
+            let bar = ProgressBar::new_spinner();
+            bar.set_message(message);
+            bar.enable_steady_tick(Duration::from_millis(100));
+            let _ = rx.recv();
+            bar.finish_and_clear();
+        });
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the spinner thread.
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
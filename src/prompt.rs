@@ -0,0 +1,56 @@
+use anyhow::{Context, Result, anyhow};
+use std::io::{BufRead, IsTerminal, Write};
+
+use crate::command_tree::ParamDef;
+
+/// Prompts need a terminal on both ends: stderr to ask, stdin to answer.
+pub fn available() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Asks for `param` on stderr until a non-empty (and, for enums, allowed) value is entered.
+pub fn param_value(param: &ParamDef) -> Result<String> {
+    let choices = param
+        .enum_values
+        .as_ref()
+        .map(|values| format!(" [{}]", values.join("|")))
+        .unwrap_or_default();
+    let stdin = std::io::stdin();
+    loop {
+        eprint!("--{} ({}){choices}: ", param.flag, param.location);
+        std::io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).context("read answer")? == 0 {
+            return Err(anyhow!("missing required argument --{}", param.flag));
+        }
+        let value = line.trim();
+        if value.is_empty() {
+            continue;
+        }
+        if let Some(values) = &param.enum_values
+            && !values.iter().any(|allowed| allowed == value)
+        {
+            eprintln!("expected one of: {}", values.join(", "));
+            continue;
+        }
+        match param.checked_value(value) {
+            Ok(value) => return Ok(value),
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+}
+
+/// Asks a yes/no question on stderr; only `y`/`yes` counts as yes.
+pub fn confirm(question: &str) -> Result<bool> {
+    eprint!("{question} [y/N] ");
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("read answer")?;
+    Ok(matches!(
+        line.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Below this many remaining requests, adaptive pacing spreads calls until the reset.
+const LOW_WATERMARK: u64 = 5;
+
+/// Client-side token buckets: one global cap plus one bucket per operation, optionally
+/// paced by the server's `X-RateLimit-*` headers.
+pub struct RateLimiter {
+    global_rate: Option<f64>,
+    adaptive: bool,
+    /// Log adaptive pacing waits to stderr (`-v`).
+    verbose: bool,
+    state: Mutex<State>,
+}
+
+struct State {
+    global: Option<Bucket>,
+    per_op: HashMap<String, Bucket>,
+    observed: Option<Observed>,
+}
+
+struct Observed {
+    remaining: u64,
+    reset_at: Instant,
+}
+
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    fn wait_time(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(global_rate: Option<f64>, adaptive: bool, verbose: bool) -> Self {
+        let global_rate = global_rate.filter(|rate| *rate > 0.0);
+        Self {
+            global_rate,
+            adaptive,
+            verbose,
+            state: Mutex::new(State {
+                global: global_rate.map(Bucket::new),
+                per_op: HashMap::new(),
+                observed: None,
+            }),
+        }
+    }
+
+    /// Records the latest `X-RateLimit-Remaining` / `X-RateLimit-Reset` values.
+    pub fn observe(&self, remaining: u64, reset_in: Duration) {
+        if !self.adaptive {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.observed = Some(Observed {
+            remaining,
+            reset_at: Instant::now() + reset_in,
+        });
+    }
+
+    fn pace(&self) {
+        let wait = {
+            let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+            let now = Instant::now();
+            let Some(observed) = state.observed.as_mut() else {
+                return;
+            };
+            let until_reset = observed.reset_at.saturating_duration_since(now);
+            if until_reset.is_zero() {
+                state.observed = None;
+                return;
+            }
+            let wait = match observed.remaining {
+                0 => until_reset,
+                n if n <= LOW_WATERMARK => until_reset / (n as u32 + 1),
+                _ => Duration::ZERO,
+            };
+            observed.remaining = observed.remaining.saturating_sub(1);
+            wait
+        };
+        if !wait.is_zero() {
+            if self.verbose {
+                eprintln!("rate limit: pacing {:.1}s", wait.as_secs_f64());
+            }
+            thread::sleep(wait);
+        }
+    }
+
+    /// Blocks until both the global bucket and the bucket for `key` have a token.
+    /// `op_rate` overrides the global rate for this operation; the global rate still caps overall.
+    pub fn acquire(&self, key: &str, op_rate: Option<f64>) {
+        if self.adaptive {
+            self.pace();
+        }
+        let op_rate = op_rate.filter(|rate| *rate > 0.0);
+        if self.global_rate.is_none() && op_rate.is_none() {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+                let now = Instant::now();
+                let State { global, per_op, .. } = &mut *state;
+
+                let mut wait = Duration::ZERO;
+                if let Some(bucket) = global.as_mut() {
+                    bucket.refill(now);
+                    wait = wait.max(bucket.wait_time());
+                }
+                let mut op_bucket = None;
+                if let Some(rate) = op_rate {
+                    let bucket = per_op
+                        .entry(key.to_string())
+                        .or_insert_with(|| Bucket::new(rate));
+                    bucket.refill(now);
+                    wait = wait.max(bucket.wait_time());
+                    op_bucket = Some(bucket);
+                }
+
+                if wait.is_zero() {
+                    if let Some(bucket) = global.as_mut() {
+                        bucket.tokens -= 1.0;
+                    }
+                    if let Some(bucket) = op_bucket {
+                        bucket.tokens -= 1.0;
+                    }
+                    return;
+                }
+                wait
+            };
+            thread::sleep(wait);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use reqwest::Url;
+use reqwest::header::HeaderMap;
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+/// Masked whatever `--header-redact` says; the flag only adds names.
+pub const ALWAYS_REDACTED: [&str; 3] = ["authorization", "proxy-authorization", "set-cookie"];
+const MASK: &str = "****";
+
+/// Header names (case-insensitive) whose values are masked in every output path.
+#[derive(Debug, Clone)]
+pub struct RedactSet {
+    names: Vec<String>,
+}
+
+impl Default for RedactSet {
+    fn default() -> Self {
+        Self::parse("")
+    }
+}
+
+impl RedactSet {
+    /// `ALWAYS_REDACTED` plus a comma-separated list such as `x-callback-token,cookie`.
+    pub fn parse(list: &str) -> Self {
+        let mut names: Vec<String> = ALWAYS_REDACTED
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        for name in list.split(',').map(|name| name.trim().to_ascii_lowercase()) {
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        Self { names }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n.eq_ignore_ascii_case(name))
+    }
+
+    pub fn mask<'a>(&self, name: &str, value: &'a str) -> Cow<'a, str> {
+        if self.contains(name) {
+            Cow::Owned(redact_secret(value))
+        } else {
+            Cow::Borrowed(value)
+        }
+    }
+
+    pub fn headers_to_json(&self, headers: &HeaderMap) -> Value {
+        let mut map = Map::new();
+        for (key, value) in headers.iter() {
+            let val = value.to_str().unwrap_or("");
+            map.insert(
+                key.to_string(),
+                Value::String(self.mask(key.as_str(), val).into_owned()),
+            );
+        }
+        Value::Object(map)
+    }
+}
+
+/// Masks a credential, keeping what only identifies its kind: an auth scheme
+/// (`Basic eG5k...` becomes `Basic ****`) or a Xendit key's environment prefix
+/// (`xnd_development_...` becomes `xnd_development_****`).
+pub fn redact_secret(value: &str) -> String {
+    if let Some((scheme, _)) = value.split_once(' ')
+        && !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return format!("{scheme} {MASK}");
+    }
+    let prefix = value
+        .strip_prefix("xnd_")
+        .and_then(|rest| rest.find('_'))
+        .map_or("", |idx| &value[..idx + 5]);
+    format!("{prefix}{MASK}")
+}
+
+/// Drops `user:password@` from a URL before it is printed.
+pub fn strip_credentials(url: &mut Url) {
+    if !url.username().is_empty() || url.password().is_some() {
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_redact_only_adds_names() {
+        let set = RedactSet::parse("X-Callback-Token, authorization");
+        for name in [
+            "Authorization",
+            "proxy-authorization",
+            "set-cookie",
+            "x-callback-token",
+        ] {
+            assert!(set.contains(name), "{name}");
+        }
+        assert!(!set.contains("content-type"));
+        assert!(RedactSet::default().contains("authorization"));
+    }
+
+    #[test]
+    fn secrets_keep_only_their_scheme_or_key_prefix() {
+        assert_eq!(
+            redact_secret("Basic eG5kX2RldmVsb3BtZW50X2FiYzo="),
+            "Basic ****"
+        );
+        assert_eq!(
+            redact_secret("xnd_development_abc123"),
+            "xnd_development_****"
+        );
+        assert_eq!(
+            redact_secret("xnd_production_abc123"),
+            "xnd_production_****"
+        );
+        assert_eq!(redact_secret("secret"), "****");
+    }
+}
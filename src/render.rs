@@ -0,0 +1,509 @@
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Number, Value, json};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use crate::http::HttpResponse;
+use crate::redact::RedactSet;
+use crate::select::Selector;
+use crate::table;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Env,
+    Yaml,
+    Table,
+    Csv,
+}
+
+impl OutputFormat {
+    pub const NAMES: [&'static str; 5] = ["json", "env", "yaml", "table", "csv"];
+
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "json" => Ok(Self::Json),
+            "env" => Ok(Self::Env),
+            "yaml" => Ok(Self::Yaml),
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            other => Err(anyhow!("unknown output format {other}")),
+        }
+    }
+}
+
+pub struct RenderOptions {
+    pub format: OutputFormat,
+    pub raw: bool,
+    pub pretty: bool,
+    pub limit_output: Option<usize>,
+    pub redact: RedactSet,
+    /// Round numbers through i64/u64/f64 instead of keeping their exact digits.
+    pub lossy_numbers: bool,
+    pub headers_only: bool,
+    /// Byte-stable output for snapshot tests: compact JSON, sorted keys, normalized numbers.
+    pub canonical: bool,
+    /// Columns for `--output table`/`csv`; empty means every key.
+    pub columns: Vec<String>,
+    /// `--select` expression applied to the body before anything else.
+    pub select: Option<Selector>,
+    /// `--pretty-numbers`: thousands separator for integers in `--output table`.
+    pub number_grouping: Option<&'static str>,
+    /// `--timing`: add a `meta` object with elapsed time and size to the `--raw` envelope.
+    pub timing: bool,
+    /// `--hyperlinks`: URL values become OSC 8 links in `table`, `yaml` and `env` output.
+    pub hyperlinks: bool,
+}
+
+/// Returns `None` when `--select` matched nothing, in which case nothing is printed.
+pub fn render_response(resp: &HttpResponse, opts: &RenderOptions) -> Result<Option<String>> {
+    if opts.headers_only {
+        return render_output(&opts.redact.headers_to_json(&resp.headers), opts).map(Some);
+    }
+
+    let mut body_value = match &opts.select {
+        Some(selector) => match selector.apply(&resp.body) {
+            Some(selected) => selected,
+            None => return Ok(None),
+        },
+        None => resp.body.clone(),
+    };
+    if opts.lossy_numbers {
+        lossy_numbers(&mut body_value);
+    }
+    if let Some(limit) = opts.limit_output {
+        body_value = limit_items(body_value, limit);
+    }
+
+    let mut output = if opts.raw {
+        let mut envelope = json!({
+            "status": resp.status,
+            "headers": opts.redact.headers_to_json(&resp.headers),
+            "body": body_value,
+        });
+        if let Some(content_type) = resp.non_json_content_type() {
+            envelope["content_type"] = json!(content_type);
+        }
+        if resp.attempts > 1 {
+            envelope["retries"] = json!(resp.attempts - 1);
+        }
+        if opts.timing {
+            envelope["meta"] = json!({
+                "elapsed_ms": resp.elapsed.as_millis() as u64,
+                "bytes": resp.raw.len(),
+                "attempts": resp.attempts,
+            });
+        }
+        envelope
+    } else {
+        body_value
+    };
+    if opts.canonical {
+        canonicalize(&mut output);
+    }
+
+    render_output(&output, opts).map(Some)
+}
+
+/// One `--stream` element as a compact JSON line, after `--select`, `--lossy-numbers` and
+/// `--canonical`. `None` when the selector matched nothing.
+pub fn render_stream_item(item: &Value, opts: &RenderOptions) -> Result<Option<String>> {
+    let mut value = match &opts.select {
+        Some(selector) => match selector.apply(item) {
+            Some(selected) => selected,
+            None => return Ok(None),
+        },
+        None => item.clone(),
+    };
+    if opts.lossy_numbers {
+        lossy_numbers(&mut value);
+    }
+    if opts.canonical {
+        canonicalize(&mut value);
+    }
+    render_value(&value, false).map(Some)
+}
+
+pub fn render_output(value: &Value, opts: &RenderOptions) -> Result<String> {
+    render_formatted(
+        value,
+        opts.format,
+        opts.pretty && !opts.canonical,
+        &opts.columns,
+        opts.number_grouping,
+        opts.hyperlinks,
+    )
+}
+
+/// Renders any JSON value in `format`; `pretty` only affects JSON (YAML is always block style).
+pub fn render_as(
+    value: &Value,
+    format: OutputFormat,
+    pretty: bool,
+    columns: &[String],
+) -> Result<String> {
+    render_formatted(value, format, pretty, columns, None, false)
+}
+
+/// `render_as` plus the terminal-only touches: grouped numbers in tables and `links` for
+/// URL values in the text formats. JSON and CSV are left machine-readable.
+fn render_formatted(
+    value: &Value,
+    format: OutputFormat,
+    pretty: bool,
+    columns: &[String],
+    grouping: Option<&str>,
+    links: bool,
+) -> Result<String> {
+    match format {
+        OutputFormat::Json => render_value(value, pretty),
+        OutputFormat::Env => render_env(value, links),
+        OutputFormat::Yaml => render_yaml(value, links),
+        OutputFormat::Table => match table::rows(value) {
+            Some(rows) => Ok(table::render(rows, columns, grouping, links)),
+            None => {
+                eprintln!("warning: response is not a list of objects; showing JSON");
+                render_value(value, true)
+            }
+        },
+        OutputFormat::Csv => table::rows(value)
+            .map(|rows| table::render_csv(rows, columns))
+            .ok_or_else(|| {
+                anyhow!("--output csv needs a list of objects; use --output json for this response")
+            }),
+    }
+}
+
+pub fn render_value(value: &Value, pretty: bool) -> Result<String> {
+    if pretty {
+        Ok(serde_json::to_string_pretty(value)?)
+    } else {
+        Ok(serde_json::to_string(value)?)
+    }
+}
+
+fn render_yaml(value: &Value, links: bool) -> Result<String> {
+    let text = serde_yaml::to_string(&to_yaml(value)).context("serialize yaml")?;
+    let text = text.trim_end_matches('\n');
+    if !links {
+        return Ok(text.to_string());
+    }
+    // The escapes can't go through serde_yaml, which would quote them, so the URL values
+    // found in `value` are linked where they end a `key: ` or `- ` line.
+    let mut urls = Vec::new();
+    collect_urls(value, &mut urls);
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let scalar = line.rfind(' ').map_or("", |idx| &line[idx + 1..]);
+            let lead = &line[..line.len() - scalar.len()];
+            if urls.contains(&scalar) && (lead.ends_with(": ") || lead.ends_with("- ")) {
+                format!("{lead}{}", hyperlink(scalar, scalar))
+            } else {
+                line.to_string()
+            }
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+fn collect_urls<'a>(value: &'a Value, urls: &mut Vec<&'a str>) {
+    match value {
+        Value::String(text) if is_url(text) => urls.push(text),
+        Value::Array(items) => items.iter().for_each(|item| collect_urls(item, urls)),
+        Value::Object(map) => map.values().for_each(|item| collect_urls(item, urls)),
+        _ => {}
+    }
+}
+
+/// `arbitrary_precision` numbers serialize as a private wrapper struct, so the value is
+/// converted by hand. Integers keep their digits; other numbers go through f64.
+fn to_yaml(value: &Value) -> serde_yaml::Value {
+    match value {
+        Value::Null => serde_yaml::Value::Null,
+        Value::Bool(b) => serde_yaml::Value::Bool(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                serde_yaml::Value::Number(i.into())
+            } else if let Some(u) = n.as_u64() {
+                serde_yaml::Value::Number(u.into())
+            } else {
+                match n.as_f64() {
+                    Some(f) if f.is_finite() => serde_yaml::Value::Number(f.into()),
+                    _ => serde_yaml::Value::String(n.to_string()),
+                }
+            }
+        }
+        Value::String(text) => serde_yaml::Value::String(text.clone()),
+        Value::Array(items) => serde_yaml::Value::Sequence(items.iter().map(to_yaml).collect()),
+        Value::Object(map) => serde_yaml::Value::Mapping(
+            map.iter()
+                .map(|(key, item)| (serde_yaml::Value::String(key.clone()), to_yaml(item)))
+                .collect(),
+        ),
+    }
+}
+
+/// Renders an object as `KEY='value'` lines for `eval`. Nested objects are flattened
+/// with underscores (`customer.email` -> `CUSTOMER_EMAIL`); arrays stay JSON text.
+fn render_env(value: &Value, links: bool) -> Result<String> {
+    if !value.is_object() {
+        return Err(anyhow!("--output env needs a JSON object response"));
+    }
+    let mut lines = Vec::new();
+    flatten_env("", value, links, &mut lines);
+    Ok(lines.join("\n"))
+}
+
+fn flatten_env(prefix: &str, value: &Value, links: bool, lines: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map {
+                let key = env_key(key);
+                let name = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}_{key}")
+                };
+                flatten_env(&name, item, links, lines);
+            }
+        }
+        Value::String(text) if links && is_url(text) => {
+            lines.push(format!("{prefix}={}", hyperlink(text, &shell_quote(text))));
+        }
+        Value::String(text) => lines.push(format!("{prefix}={}", shell_quote(text))),
+        Value::Null => lines.push(format!("{prefix}=")),
+        other => lines.push(format!("{prefix}={}", shell_quote(&other.to_string()))),
+    }
+}
+
+fn env_key(key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if key.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{key}")
+    } else {
+        key
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Numbers are parsed with `arbitrary_precision`, so large IDs and amounts keep their
+/// exact digits; this restores the old 64-bit behaviour on request.
+fn lossy_numbers(value: &mut Value) {
+    match value {
+        Value::Number(n) => {
+            if n.as_i64().is_none()
+                && n.as_u64().is_none()
+                && let Some(lossy) = n.as_f64().and_then(Number::from_f64)
+            {
+                *n = lossy;
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(lossy_numbers),
+        Value::Object(map) => map.values_mut().for_each(lossy_numbers),
+        _ => {}
+    }
+}
+
+/// Sorts object keys and rewrites numbers into one spelling per value: integral values
+/// (`1.0`, `1e2`) become plain integers, everything else the shortest f64 round-trip form.
+/// A number whose exact value that would change (more digits than an f64 holds, or an
+/// integer past i64/u64) keeps its original text.
+fn canonicalize(value: &mut Value) {
+    match value {
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                *n = Number::from(i);
+            } else if let Some(u) = n.as_u64() {
+                *n = Number::from(u);
+            } else if let Some(f) = n.as_f64() {
+                let integral = f.fract() == 0.0 && f.abs() < 9_007_199_254_740_992.0;
+                let normalized = if integral {
+                    Some(Number::from(f as i64))
+                } else {
+                    Number::from_f64(f)
+                };
+                if let Some(normalized) = normalized
+                    && exact_decimal(&normalized.to_string()) == exact_decimal(&n.to_string())
+                {
+                    *n = normalized;
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(canonicalize),
+        Value::Object(map) => {
+            map.sort_keys();
+            map.values_mut().for_each(canonicalize);
+        }
+        _ => {}
+    }
+}
+
+/// A JSON number's exact value as (negative, significant digits, power of ten), so two
+/// spellings compare equal exactly when they denote the same decimal.
+fn exact_decimal(text: &str) -> Option<(bool, String, i64)> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int}{frac}");
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    let exponent = exponent - frac.len() as i64 + (digits.len() - significant.len()) as i64;
+    Some((negative, significant.to_string(), exponent))
+}
+
+/// Truncates a top-level array (or an object's `data` array) to `limit` items.
+/// Objects get a `_truncated` marker; bare arrays can't carry one, so the note goes to stderr.
+fn limit_items(value: Value, limit: usize) -> Value {
+    match value {
+        Value::Array(mut items) => {
+            let total = items.len();
+            if total > limit {
+                items.truncate(limit);
+                eprintln!("(showing {limit} of {total})");
+            }
+            Value::Array(items)
+        }
+        Value::Object(mut map) => {
+            let mut total = None;
+            if let Some(Value::Array(items)) = map.get_mut("data")
+                && items.len() > limit
+            {
+                total = Some(items.len());
+                items.truncate(limit);
+            }
+            if let Some(total) = total {
+                map.insert(
+                    "_truncated".to_string(),
+                    json!({"shown": limit, "total": total}),
+                );
+            }
+            Value::Object(map)
+        }
+        other => other,
+    }
+}
+
+/// Pipes rendered output through `sh -c <command>` and returns its stdout.
+/// Runs after every built-in output transform.
+pub fn transform_output(command: &str, input: &str) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("spawn transform `{command}`"))?;
+
+    let mut stdin = child.stdin.take().context("open transform stdin")?;
+    let payload = format!("{input}\n");
+    let writer = thread::spawn(move || {
+        // The transformer may exit without reading everything; its exit status decides.
+        let _ = stdin.write_all(payload.as_bytes());
+    });
+
+    let output = child.wait_with_output().context("wait for transform")?;
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(anyhow!("transform `{command}` failed: {}", output.status));
+    }
+    let text = String::from_utf8(output.stdout).context("transform output is not UTF-8")?;
+    Ok(text.trim_end_matches('\n').to_string())
+}
+
+/// Whether a string value is a URL worth linking. Whitespace and control characters are
+/// ruled out so the value can't break out of the escape sequence.
+pub fn is_url(text: &str) -> bool {
+    (text.starts_with("http://") || text.starts_with("https://"))
+        && !text.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+/// `label` as an OSC 8 hyperlink to `url`, for terminals that render them as clickable.
+pub fn hyperlink(url: &str, label: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{label}\x1b]8;;\x1b\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(raw: &str) -> String {
+        let mut value: Value = serde_json::from_str(raw).unwrap();
+        canonicalize(&mut value);
+        value.to_string()
+    }
+
+    #[test]
+    fn sorts_keys_and_normalizes_exact_numbers() {
+        assert_eq!(
+            canonical(r#"{"b": 1.0, "a": [1e2, 2.50, -0.0]}"#),
+            r#"{"a":[100,2.5,0],"b":1}"#
+        );
+    }
+
+    #[test]
+    fn keeps_numbers_an_f64_would_round() {
+        let raw =
+            r#"[0.12345678901234567890, 123456789012345678901234567890, 1.0000000000000000001]"#;
+        assert_eq!(
+            canonical(raw),
+            "[0.12345678901234567890,123456789012345678901234567890,1.0000000000000000001]"
+        );
+    }
+
+    #[test]
+    fn hyperlinks_wrap_url_values_in_text_formats() {
+        let url = "https://checkout.xendit.co/web/inv-1";
+        let link = hyperlink(url, url);
+        let value = json!({"id": "inv-1", "invoice_url": url, "note": "see http://x y"});
+        assert_eq!(
+            render_yaml(&value, true).unwrap(),
+            format!("id: inv-1\ninvoice_url: {link}\nnote: see http://x y")
+        );
+        assert_eq!(
+            render_env(&value, true).unwrap(),
+            format!(
+                "ID='inv-1'\nINVOICE_URL={}\nNOTE='see http://x y'",
+                hyperlink(url, &format!("'{url}'"))
+            )
+        );
+        let rows = json!([{"id": "inv-1", "url": "https://x.co"}]);
+        assert_eq!(
+            table::render(rows.as_array().unwrap(), &[], None, true),
+            format!(
+                "id     url\ninv-1  {}",
+                hyperlink("https://x.co", "https://x.co")
+            )
+        );
+        assert!(!render_yaml(&value, false).unwrap().contains('\x1b'));
+    }
+
+    #[test]
+    fn exact_decimal_ignores_spelling() {
+        assert_eq!(exact_decimal("1.50"), exact_decimal("15e-1"));
+        assert_eq!(exact_decimal("0"), exact_decimal("-0.000"));
+        assert_ne!(exact_decimal("1.5"), exact_decimal("-1.5"));
+    }
+}
@@ -0,0 +1,325 @@
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+use crate::command_tree::{CommandTree, Operation, ParamDef};
+
+/// Structural differences between two command trees. Descriptions and examples are
+/// ignored; ops are matched by resource and op name.
+#[derive(Default)]
+pub struct SchemaDiff {
+    pub added_resources: Vec<String>,
+    pub removed_resources: Vec<String>,
+    /// `(resource, op)` pairs, only for resources present in both trees.
+    pub added_ops: Vec<(String, String)>,
+    pub removed_ops: Vec<(String, String)>,
+    pub changed_ops: Vec<OpChanges>,
+}
+
+pub struct OpChanges {
+    pub resource: String,
+    pub op: String,
+    /// One line per change, e.g. `method: GET -> POST` or `--limit: added (query)`.
+    pub changes: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_resources.is_empty()
+            && self.removed_resources.is_empty()
+            && self.added_ops.is_empty()
+            && self.removed_ops.is_empty()
+            && self.changed_ops.is_empty()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let pairs = |ops: &[(String, String)]| -> Vec<Value> {
+            ops.iter()
+                .map(|(resource, op)| json!({"resource": resource, "op": op}))
+                .collect()
+        };
+        json!({
+            "added_resources": self.added_resources,
+            "removed_resources": self.removed_resources,
+            "added_ops": pairs(&self.added_ops),
+            "removed_ops": pairs(&self.removed_ops),
+            "changed_ops": self.changed_ops.iter().map(|changed| json!({
+                "resource": changed.resource,
+                "op": changed.op,
+                "changes": changed.changes,
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    /// `+`/`-`/`~` lines in the style of a unified diff.
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        lines.extend(self.added_resources.iter().map(|res| format!("+ {res}")));
+        lines.extend(self.removed_resources.iter().map(|res| format!("- {res}")));
+        lines.extend(
+            self.added_ops
+                .iter()
+                .map(|(res, op)| format!("+ {res} {op}")),
+        );
+        lines.extend(
+            self.removed_ops
+                .iter()
+                .map(|(res, op)| format!("- {res} {op}")),
+        );
+        for changed in &self.changed_ops {
+            lines.push(format!("~ {} {}", changed.resource, changed.op));
+            lines.extend(changed.changes.iter().map(|change| format!("    {change}")));
+        }
+        lines
+    }
+}
+
+pub fn diff(old: &CommandTree, new: &CommandTree) -> SchemaDiff {
+    let old_resources: BTreeMap<&str, &[Operation]> = old
+        .resources
+        .iter()
+        .map(|res| (res.name.as_str(), res.ops.as_slice()))
+        .collect();
+    let new_resources: BTreeMap<&str, &[Operation]> = new
+        .resources
+        .iter()
+        .map(|res| (res.name.as_str(), res.ops.as_slice()))
+        .collect();
+
+    let mut out = SchemaDiff::default();
+    for name in old_resources.keys() {
+        if !new_resources.contains_key(name) {
+            out.removed_resources.push(name.to_string());
+        }
+    }
+    for (name, new_ops) in &new_resources {
+        let Some(old_ops) = old_resources.get(name) else {
+            out.added_resources.push(name.to_string());
+            continue;
+        };
+        let old_ops: BTreeMap<&str, &Operation> =
+            old_ops.iter().map(|op| (op.name.as_str(), op)).collect();
+        let new_ops: BTreeMap<&str, &Operation> =
+            new_ops.iter().map(|op| (op.name.as_str(), op)).collect();
+        for op in old_ops.keys() {
+            if !new_ops.contains_key(op) {
+                out.removed_ops.push((name.to_string(), op.to_string()));
+            }
+        }
+        for (op_name, new_op) in &new_ops {
+            match old_ops.get(op_name) {
+                None => out.added_ops.push((name.to_string(), op_name.to_string())),
+                Some(old_op) => {
+                    let changes = op_changes(old_op, new_op);
+                    if !changes.is_empty() {
+                        out.changed_ops.push(OpChanges {
+                            resource: name.to_string(),
+                            op: op_name.to_string(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+fn op_changes(old: &Operation, new: &Operation) -> Vec<String> {
+    let mut changes = Vec::new();
+    if !old.method.eq_ignore_ascii_case(&new.method) {
+        changes.push(format!("method: {} -> {}", old.method, new.method));
+    }
+    if old.path != new.path {
+        changes.push(format!("path: {} -> {}", old.path, new.path));
+    }
+    if body_label(old) != body_label(new) {
+        changes.push(format!("body: {} -> {}", body_label(old), body_label(new)));
+    }
+    match (&old.deprecated, &new.deprecated) {
+        (None, Some(_)) => changes.push("deprecated".to_string()),
+        (Some(_), None) => changes.push("no longer deprecated".to_string()),
+        _ => {}
+    }
+
+    for param in &old.params {
+        if !new.params.iter().any(|other| same_param(param, other)) {
+            changes.push(format!("--{}: removed ({})", param.flag, param.location));
+        }
+    }
+    for param in &new.params {
+        match old.params.iter().find(|other| same_param(param, other)) {
+            None => changes.push(format!("--{}: added ({})", param.flag, param_label(param))),
+            Some(old_param) => {
+                if old_param.flag != param.flag {
+                    changes.push(format!("--{}: renamed to --{}", old_param.flag, param.flag));
+                }
+                if param_label(old_param) != param_label(param) {
+                    changes.push(format!(
+                        "--{}: {} -> {}",
+                        param.flag,
+                        param_label(old_param),
+                        param_label(param)
+                    ));
+                }
+            }
+        }
+    }
+    changes
+}
+
+fn same_param(a: &ParamDef, b: &ParamDef) -> bool {
+    a.name == b.name && a.location == b.location
+}
+
+fn body_label(op: &Operation) -> &'static str {
+    if op.is_multipart() {
+        "multipart"
+    } else if op.is_merge_patch() {
+        "merge-patch"
+    } else if op.has_body {
+        "json"
+    } else {
+        "none"
+    }
+}
+
+/// Location plus the attributes that change how the flag behaves, e.g.
+/// `query, integer, required, one of: A|B, default: 10`.
+fn param_label(param: &ParamDef) -> String {
+    let mut parts = vec![param.location.clone()];
+    if param.value_type() != "string" {
+        parts.push(param.value_type().to_string());
+    }
+    if param.required {
+        parts.push("required".to_string());
+    }
+    if param.multiple {
+        parts.push("repeatable".to_string());
+    }
+    if let Some(values) = &param.enum_values {
+        parts.push(format!("one of: {}", values.join("|")));
+    }
+    if let Some(default) = &param.default {
+        parts.push(format!("default: {default}"));
+    }
+    if let Some(pattern) = &param.pattern {
+        parts.push(format!("pattern: {pattern}"));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn old_tree() -> CommandTree {
+        serde_json::from_value(json!({
+            "version": 1,
+            "base_url": "https://api.xendit.co",
+            "resources": [
+                {"name": "balance", "ops": [
+                    {"name": "get-balance", "method": "GET", "path": "/balance",
+                     "params": [], "has_body": false},
+                ]},
+                {"name": "invoices", "ops": [
+                    {"name": "create", "method": "POST", "path": "/v2/invoices",
+                     "params": [], "has_body": true},
+                    {"name": "expire", "method": "POST", "path": "/v2/invoices/{id}/expire",
+                     "params": [], "has_body": false},
+                    {"name": "get", "method": "GET", "path": "/v2/invoices/{id}",
+                     "params": [], "has_body": false, "deprecated": ""},
+                    {"name": "list", "method": "GET", "path": "/v2/invoices", "has_body": false,
+                     "params": [
+                         {"name": "limit", "flag": "limit", "location": "query", "required": false},
+                         {"name": "status", "flag": "status", "location": "query",
+                          "required": false},
+                         {"name": "after_id", "flag": "after-id", "location": "query",
+                          "required": false},
+                         {"name": "created_after", "flag": "created-after", "location": "query",
+                          "required": false},
+                     ]},
+                    {"name": "upload", "method": "PUT", "path": "/v2/invoices/attachments",
+                     "params": [], "has_body": false},
+                ]},
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn new_tree() -> CommandTree {
+        serde_json::from_value(json!({
+            "version": 1,
+            "base_url": "https://api.xendit.co",
+            "resources": [
+                {"name": "invoices", "ops": [
+                    {"name": "create", "method": "POST", "path": "/v2/invoices", "params": [],
+                     "has_body": true, "body_content_type": "application/merge-patch+json"},
+                    {"name": "get", "method": "GET", "path": "/v3/invoices/{id}",
+                     "params": [], "has_body": false},
+                    {"name": "list", "method": "GET", "path": "/v2/invoices", "has_body": false,
+                     "deprecated": "use search",
+                     "params": [
+                         {"name": "limit", "flag": "page-size", "location": "query",
+                          "required": false},
+                         {"name": "status", "flag": "status", "location": "query",
+                          "required": true},
+                         {"name": "currency", "flag": "currency", "location": "query",
+                          "required": false, "enum_values": ["IDR", "PHP"]},
+                         {"name": "created_from", "flag": "created-after", "location": "query",
+                          "required": false},
+                     ]},
+                    {"name": "search", "method": "GET", "path": "/v2/invoices/search",
+                     "params": [], "has_body": false},
+                    {"name": "upload", "method": "POST", "path": "/v2/invoices/attachments",
+                     "params": [], "has_body": true, "body_kind": "multipart"},
+                ]},
+                {"name": "payouts", "ops": []},
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_entries() {
+        assert_eq!(
+            diff(&old_tree(), &new_tree()).to_lines(),
+            [
+                "+ payouts",
+                "- balance",
+                "+ invoices search",
+                "- invoices expire",
+                "~ invoices create",
+                "    body: json -> merge-patch",
+                "~ invoices get",
+                "    path: /v2/invoices/{id} -> /v3/invoices/{id}",
+                "    no longer deprecated",
+                "~ invoices list",
+                "    deprecated",
+                "    --after-id: removed (query)",
+                // Params are matched by API name, so a renamed param is a removal plus an
+                // addition even when its flag stays the same.
+                "    --created-after: removed (query)",
+                "    --limit: renamed to --page-size",
+                "    --status: query -> query, required",
+                "    --currency: added (query, one of: IDR|PHP)",
+                "    --created-after: added (query)",
+                "~ invoices upload",
+                "    method: PUT -> POST",
+                "    body: none -> multipart",
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_trees_have_no_differences() {
+        let changes = diff(&new_tree(), &new_tree());
+        assert!(changes.is_empty());
+        assert_eq!(
+            changes.to_json(),
+            json!({
+                "added_resources": [], "removed_resources": [], "added_ops": [],
+                "removed_ops": [], "changed_ops": [],
+            })
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// A jq-lite expression for `--select`: dot access (`.data`), indexing (`[0]`, `[-1]`),
+/// quoted keys (`["key"]`), iteration (`[]`), and `select(.path OP literal)` stages
+/// joined with `|`. Expressions that iterate produce an array of every match.
+#[derive(Debug, Clone)]
+pub struct Selector {
+    stages: Vec<Stage>,
+    iterates: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Stage {
+    Path(Vec<Step>),
+    Select(Filter),
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+#[derive(Debug, Clone)]
+struct Filter {
+    path: Vec<Step>,
+    /// `None` keeps values whose path is truthy (not null/false).
+    compare: Option<(CmpOp, Value)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+const OPERATORS: [(&str, CmpOp); 6] = [
+    ("==", CmpOp::Eq),
+    ("!=", CmpOp::Ne),
+    (">=", CmpOp::Ge),
+    ("<=", CmpOp::Le),
+    (">", CmpOp::Gt),
+    ("<", CmpOp::Lt),
+];
+
+impl Selector {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut stages = Vec::new();
+        for stage in split_outside_quotes(expr, '|') {
+            let stage = stage.trim();
+            if stage.is_empty() {
+                return Err(anyhow!("invalid --select {expr:?}: empty stage"));
+            }
+            let parsed = if let Some(inner) = stage
+                .strip_prefix("select(")
+                .and_then(|rest| rest.strip_suffix(')'))
+            {
+                Stage::Select(
+                    parse_filter(inner)
+                        .map_err(|err| anyhow!("invalid --select {expr:?}: {err}"))?,
+                )
+            } else {
+                Stage::Path(
+                    parse_path(stage).map_err(|err| anyhow!("invalid --select {expr:?}: {err}"))?,
+                )
+            };
+            stages.push(parsed);
+        }
+        let iterates = stages.iter().any(|stage| match stage {
+            Stage::Path(steps) => steps.iter().any(|step| matches!(step, Step::Iterate)),
+            Stage::Select(_) => false,
+        });
+        Ok(Self { stages, iterates })
+    }
+
+    /// Applies the expression; `None` means nothing matched.
+    pub fn apply(&self, value: &Value) -> Option<Value> {
+        let mut stream = vec![value.clone()];
+        for stage in &self.stages {
+            stream = match stage {
+                Stage::Path(steps) => stream.iter().flat_map(|item| walk(item, steps)).collect(),
+                Stage::Select(filter) => stream
+                    .into_iter()
+                    .filter(|item| filter.matches(item))
+                    .collect(),
+            };
+        }
+        if stream.is_empty() {
+            None
+        } else if self.iterates {
+            Some(Value::Array(stream))
+        } else {
+            stream.into_iter().next()
+        }
+    }
+}
+
+/// A `--poll-until` or `--assert` condition: `.path OP literal`, or a bare `.path` that must be truthy
+/// (not null/false). Uses the same paths and literals as `select(...)`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    expr: String,
+    filter: Filter,
+}
+
+impl Condition {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let filter =
+            parse_filter(expr).map_err(|err| anyhow!("invalid condition {expr:?}: {err}"))?;
+        Ok(Self {
+            expr: expr.trim().to_string(),
+            filter,
+        })
+    }
+
+    pub fn matches(&self, value: &Value) -> bool {
+        self.filter.matches(value)
+    }
+
+    /// The value the condition's path currently points at, for progress output.
+    pub fn observed(&self, value: &Value) -> Value {
+        walk(value, &self.filter.path)
+            .into_iter()
+            .next()
+            .unwrap_or(Value::Null)
+    }
+
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+}
+
+impl Filter {
+    fn matches(&self, value: &Value) -> bool {
+        let found = walk(value, &self.path).into_iter().next();
+        match &self.compare {
+            None => !matches!(found, None | Some(Value::Null) | Some(Value::Bool(false))),
+            Some((op, expected)) => {
+                let actual = found.unwrap_or(Value::Null);
+                match op {
+                    CmpOp::Eq => compare(&actual, expected) == Some(Ordering::Equal),
+                    CmpOp::Ne => compare(&actual, expected) != Some(Ordering::Equal),
+                    CmpOp::Ge => matches!(
+                        compare(&actual, expected),
+                        Some(Ordering::Greater | Ordering::Equal)
+                    ),
+                    CmpOp::Le => matches!(
+                        compare(&actual, expected),
+                        Some(Ordering::Less | Ordering::Equal)
+                    ),
+                    CmpOp::Gt => compare(&actual, expected) == Some(Ordering::Greater),
+                    CmpOp::Lt => compare(&actual, expected) == Some(Ordering::Less),
+                }
+            }
+        }
+    }
+}
+
+/// Numbers compare numerically (their text differs under `arbitrary_precision`), strings
+/// lexically; anything else only by equality.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (a, b) if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn walk(value: &Value, steps: &[Step]) -> Vec<Value> {
+    let Some((step, rest)) = steps.split_first() else {
+        return vec![value.clone()];
+    };
+    match step {
+        Step::Key(key) => value
+            .get(key)
+            .map(|item| walk(item, rest))
+            .unwrap_or_default(),
+        Step::Index(index) => {
+            let Some(items) = value.as_array() else {
+                return Vec::new();
+            };
+            let resolved = if *index < 0 {
+                items.len().checked_sub(index.unsigned_abs() as usize)
+            } else {
+                Some(*index as usize)
+            };
+            resolved
+                .and_then(|idx| items.get(idx))
+                .map(|item| walk(item, rest))
+                .unwrap_or_default()
+        }
+        Step::Iterate => match value {
+            Value::Array(items) => items.iter().flat_map(|item| walk(item, rest)).collect(),
+            Value::Object(map) => map.values().flat_map(|item| walk(item, rest)).collect(),
+            _ => Vec::new(),
+        },
+    }
+}
+
+fn parse_filter(inner: &str) -> Result<Filter> {
+    let inner = inner.trim();
+    let Some((pos, op_text, op)) = find_operator(inner) else {
+        return Ok(Filter {
+            path: parse_path(inner)?,
+            compare: None,
+        });
+    };
+    let path = parse_path(inner[..pos].trim())?;
+    let literal = inner[pos + op_text.len()..].trim();
+    let expected = serde_json::from_str(literal)
+        .map_err(|_| anyhow!("expected a JSON literal after {op_text}, got {literal:?}"))?;
+    Ok(Filter {
+        path,
+        compare: Some((op, expected)),
+    })
+}
+
+fn find_operator(text: &str) -> Option<(usize, &'static str, CmpOp)> {
+    for (pos, _) in unquoted(text) {
+        for (op_text, op) in OPERATORS {
+            if text[pos..].starts_with(op_text) {
+                return Some((pos, op_text, op));
+            }
+        }
+    }
+    None
+}
+
+fn parse_path(text: &str) -> Result<Vec<Step>> {
+    let Some(mut rest) = text.strip_prefix('.') else {
+        return Err(anyhow!("path must start with '.', got {text:?}"));
+    };
+    let mut steps = Vec::new();
+    loop {
+        if rest.is_empty() {
+            return Ok(steps);
+        }
+        if let Some(after) = rest.strip_prefix('[') {
+            let (end, _) = unquoted(after)
+                .find(|&(_, c)| c == ']')
+                .ok_or_else(|| anyhow!("unclosed '[' in {text:?}"))?;
+            let inner = after[..end].trim();
+            steps.push(if inner.is_empty() {
+                Step::Iterate
+            } else if inner.starts_with('"') {
+                Step::Key(
+                    serde_json::from_str(inner)
+                        .map_err(|_| anyhow!("invalid quoted key {inner} in {text:?}"))?,
+                )
+            } else {
+                Step::Index(
+                    inner
+                        .parse()
+                        .map_err(|_| anyhow!("invalid index [{inner}] in {text:?}"))?,
+                )
+            });
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+            if rest.is_empty() || rest.starts_with(['.', ']']) {
+                return Err(anyhow!("empty key in {text:?}"));
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                let found = rest.chars().next().unwrap_or_default();
+                return Err(anyhow!("unexpected {found:?} in {text:?}"));
+            }
+            steps.push(Step::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (pos, _) in unquoted(text).filter(|&(_, c)| c == separator) {
+        parts.push(&text[start..pos]);
+        start = pos + separator.len_utf8();
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// The characters of `text` outside JSON string literals, with their byte offsets. Quotes
+/// themselves are skipped, and `\"` inside a string doesn't end it.
+fn unquoted(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    text.char_indices().filter(move |&(_, c)| {
+        if escaped {
+            escaped = false;
+        } else if in_quotes {
+            match c {
+                '\\' => escaped = true,
+                '"' => in_quotes = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else {
+            return true;
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn select(expr: &str, value: &Value) -> Option<Value> {
+        Selector::parse(expr).unwrap().apply(value)
+    }
+
+    #[test]
+    fn dot_access_and_indexing() {
+        let body = json!({"data": [{"id": "a"}, {"id": "b"}, {"id": "c"}], "has_more": false});
+        assert_eq!(select(".data[0].id", &body), Some(json!("a")));
+        assert_eq!(select(".data[-1].id", &body), Some(json!("c")));
+        assert_eq!(select(".has_more", &body), Some(json!(false)));
+        assert_eq!(select(".", &body), Some(body.clone()));
+    }
+
+    #[test]
+    fn iteration_collects_every_match() {
+        let body = json!({"data": [{"id": "a"}, {"id": "b"}]});
+        assert_eq!(select(".data[].id", &body), Some(json!(["a", "b"])));
+        assert_eq!(select(".data[] | .id", &body), Some(json!(["a", "b"])));
+    }
+
+    #[test]
+    fn quoted_keys_may_hold_brackets_pipes_and_escaped_quotes() {
+        let body = json!({"a]b": 1, "x|y": 2, "say \"hi\"": 3});
+        assert_eq!(select(r#".["a]b"]"#, &body), Some(json!(1)));
+        assert_eq!(select(r#".["x|y"]"#, &body), Some(json!(2)));
+        assert_eq!(select(r#".["say \"hi\""]"#, &body), Some(json!(3)));
+    }
+
+    #[test]
+    fn select_compares_against_literals() {
+        let body = json!({"data": [
+            {"name": "x\"|y", "amount": 10000},
+            {"name": "b", "amount": 2.5e4},
+            {"name": "c", "amount": 50000, "paid": true},
+        ]});
+        assert_eq!(
+            select(r#".data[] | select(.name == "x\"|y") | .amount"#, &body),
+            Some(json!([10000]))
+        );
+        assert_eq!(
+            select(".data[] | select(.amount >= 25000) | .name", &body),
+            Some(json!(["b", "c"]))
+        );
+        assert_eq!(
+            select(r#".data[] | select(.name != "b") | .name"#, &body),
+            Some(json!(["x\"|y", "c"]))
+        );
+        assert_eq!(
+            select(".data[] | select(.paid) | .name", &body),
+            Some(json!(["c"]))
+        );
+    }
+
+    #[test]
+    fn matching_nothing_yields_nothing() {
+        let body = json!({"data": []});
+        assert_eq!(select(".data[].id", &body), None);
+        assert_eq!(select(".missing", &body), None);
+        assert_eq!(select(".data[3]", &body), None);
+    }
+
+    #[test]
+    fn syntax_errors_fail_at_parse_time() {
+        for expr in [
+            "data",
+            ".data[",
+            ".data[x]",
+            ".data..id",
+            ".data |",
+            r#".[unterminated"]"#,
+            "select(.amount > ten)",
+        ] {
+            assert!(Selector::parse(expr).is_err(), "{expr} should not parse");
+        }
+    }
+}
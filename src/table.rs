@@ -0,0 +1,180 @@
+use serde_json::Value;
+
+/// Longest cell, in characters, before it is cut with an ellipsis.
+const MAX_CELL_WIDTH: usize = 40;
+
+/// `--locale` names for `--pretty-numbers`.
+pub const LOCALES: [&str; 4] = ["en", "id", "fr", "ch"];
+
+/// Thousands separator for a `--locale`: `1,000,000` (en), `1.000.000` (id),
+/// `1 000 000` (fr) or `1'000'000` (ch).
+pub fn group_separator(locale: &str) -> &'static str {
+    match locale {
+        "id" => ".",
+        "fr" => " ",
+        "ch" => "'",
+        _ => ",",
+    }
+}
+
+/// Rows of a tabular body: a top-level array of objects, or an object's `data` array.
+pub fn rows(value: &Value) -> Option<&Vec<Value>> {
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(map) => map.get("data")?.as_array()?,
+        _ => return None,
+    };
+    items.iter().all(Value::is_object).then_some(items)
+}
+
+/// Renders `rows` as left-aligned columns. Without explicit `columns`, uses the union of
+/// keys in first-seen order. `grouping` separates thousands in integer cells; `links`
+/// makes URL cells OSC 8 hyperlinks to the full value, even when the cell is cut.
+pub fn render(rows: &[Value], columns: &[String], grouping: Option<&str>, links: bool) -> String {
+    let columns = resolve_columns(rows, columns);
+
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            columns
+                .iter()
+                .map(|column| cell(row.get(column.as_str()), grouping))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            cells
+                .iter()
+                .map(|row| row[idx].chars().count())
+                .chain([column.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut lines = vec![format_line(&columns, &widths, &[])];
+    lines.extend(rows.iter().zip(&cells).map(|(row, cells)| {
+        let targets: Vec<Option<&str>> = columns
+            .iter()
+            .map(|column| {
+                row.get(column.as_str())
+                    .and_then(Value::as_str)
+                    .filter(|text| links && crate::render::is_url(text))
+            })
+            .collect();
+        format_line(cells, &widths, &targets)
+    }));
+    lines.join("\n")
+}
+
+/// Renders `rows` as RFC 4180 CSV with a header line; nested values become JSON text.
+pub fn render_csv(rows: &[Value], columns: &[String]) -> String {
+    let columns = resolve_columns(rows, columns);
+    let mut lines = vec![csv_line(columns.iter().map(String::as_str))];
+    for row in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| match row.get(column.as_str()) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+            })
+            .collect();
+        lines.push(csv_line(cells.iter().map(String::as_str)));
+    }
+    lines.join("\n")
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    cells.map(csv_field).collect::<Vec<_>>().join(",")
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) || text.starts_with(' ') || text.ends_with(' ') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Explicit `columns`, else the union of keys in first-seen order.
+fn resolve_columns(rows: &[Value], columns: &[String]) -> Vec<String> {
+    if !columns.is_empty() {
+        return columns.to_vec();
+    }
+    let mut seen = Vec::new();
+    for row in rows {
+        if let Value::Object(map) = row {
+            for key in map.keys() {
+                if !seen.contains(key) {
+                    seen.push(key.clone());
+                }
+            }
+        }
+    }
+    seen
+}
+
+fn cell(value: Option<&Value>, grouping: Option<&str>) -> String {
+    let text = match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Number(number)) => match grouping {
+            Some(separator) => group_digits(&number.to_string(), separator),
+            None => number.to_string(),
+        },
+        Some(other) => other.to_string(),
+    };
+    let text = text.replace(['\n', '\t'], " ");
+    if text.chars().count() > MAX_CELL_WIDTH {
+        let cut: String = text.chars().take(MAX_CELL_WIDTH - 1).collect();
+        format!("{cut}…")
+    } else {
+        text
+    }
+}
+
+/// Inserts `separator` every three digits of an integer; anything else (decimals,
+/// exponents) is returned unchanged.
+fn group_digits(number: &str, separator: &str) -> String {
+    let (sign, digits) = match number.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", number),
+    };
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return number.to_string();
+    }
+    let mut grouped = String::from(sign);
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Pads each cell to its column; a cell with a link target is wrapped before padding so
+/// the escapes don't count toward the width.
+fn format_line(cells: &[String], widths: &[usize], targets: &[Option<&str>]) -> String {
+    let line: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(idx, (cell, width))| {
+            let padded = format!("{cell:<width$}");
+            match targets.get(idx).copied().flatten() {
+                Some(url) => format!(
+                    "{}{}",
+                    crate::render::hyperlink(url, cell),
+                    &padded[cell.len()..]
+                ),
+                None => padded,
+            }
+        })
+        .collect();
+    line.join("  ").trim_end().to_string()
+}
@@ -0,0 +1,25 @@
+/// Header Xendit sets on every callback to the account's webhook verification token.
+pub const CALLBACK_TOKEN_HEADER: &str = "x-callback-token";
+
+/// Xendit doesn't sign payloads; a callback is authentic when its `x-callback-token`
+/// equals the verification token from the dashboard. Compared in constant time.
+pub fn token_matches(expected: &str, received: &str) -> bool {
+    let (a, b) = (expected.as_bytes(), received.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Finds `name` in `name=value` or `name: value` header specs (case-insensitive).
+pub fn header_value<'a>(specs: &'a [String], name: &str) -> Option<&'a str> {
+    specs.iter().find_map(|spec| {
+        let (key, value) = spec
+            .split_once(':')
+            .filter(|(key, _)| !key.contains('='))
+            .or_else(|| spec.split_once('='))?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
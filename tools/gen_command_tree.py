@@ -141,6 +141,27 @@ def postman_base_url(spec: Dict) -> str:
     return "https://api.xendit.co"
 
 
+def openapi_declares_external_id(spec: Dict, content: Dict) -> bool:
+    media = content.get("application/json") or content.get("application/merge-patch+json") or {}
+    schema = media.get("schema") or {}
+    ref = schema.get("$ref")
+    if isinstance(ref, str) and ref.startswith("#/"):
+        schema = spec
+        for part in ref[2:].split("/"):
+            schema = schema.get(part) if isinstance(schema, dict) else None
+    return isinstance(schema, dict) and "external_id" in (schema.get("properties") or {})
+
+
+def postman_declares_external_id(body: Dict) -> bool:
+    if body.get("mode") != "raw":
+        return False
+    try:
+        sample = json.loads(body.get("raw") or "")
+    except ValueError:
+        return False
+    return isinstance(sample, dict) and "external_id" in sample
+
+
 def build_from_openapi(spec: Dict) -> Dict:
     resources: Dict[str, Dict] = {}
     seen: Dict[str, set] = {}
@@ -158,6 +179,11 @@ def build_from_openapi(spec: Dict) -> Dict:
             details = details or {}
             tags = details.get("tags") or []
             resource = camel_to_kebab(tags[0]) if tags else camel_to_kebab(path.split("/")[1] or "root")
+            # Tags after the first become tags on the resource.
+            entry = resources.setdefault(resource, {"name": resource, "ops": []})
+            for tag in map(camel_to_kebab, tags[1:]):
+                if tag and tag not in entry.get("tags", []):
+                    entry.setdefault("tags", []).append(tag)
             op_name = details.get("operationId") or f"{method}-{path}"
             op_name = normalize_op_name(op_name)
 
@@ -174,29 +200,39 @@ def build_from_openapi(spec: Dict) -> Dict:
             for param in details.get("parameters") or []:
                 name = param.get("name")
                 location = param.get("in")
-                if not name or location not in {"path", "query"}:
+                if not name or location not in {"path", "query", "header"}:
                     continue
-                params.append(
-                    {
-                        "name": name,
-                        "flag": camel_to_kebab(name),
-                        "location": location,
-                        "required": bool(param.get("required")) or location == "path",
-                    }
-                )
+                param_entry = {
+                    "name": name,
+                    "flag": camel_to_kebab(name),
+                    "location": location,
+                    "required": bool(param.get("required")) or location == "path",
+                }
+                schema = param.get("schema") or {}
+                value_type = (schema.get("items") or {}).get("type") or schema.get("type")
+                if value_type in {"integer", "number", "boolean"}:
+                    param_entry["value_type"] = value_type
+                params.append(param_entry)
 
             has_body = bool(details.get("requestBody"))
-            add_op(
-                resource,
-                {
-                    "name": op_name,
-                    "method": method.upper(),
-                    "path": path,
-                    "description": details.get("summary") or details.get("description"),
-                    "params": params,
-                    "has_body": has_body,
-                },
-            )
+            op = {
+                "name": op_name,
+                "method": method.upper(),
+                "path": path,
+                "description": details.get("summary") or details.get("description"),
+                "params": params,
+                "has_body": has_body,
+            }
+            content = (details.get("requestBody") or {}).get("content") or {}
+            if "multipart/form-data" in content:
+                op["body_kind"] = "multipart"
+            elif "application/merge-patch+json" in content and "application/json" not in content:
+                op["body_content_type"] = "application/merge-patch+json"
+            if "multipart/form-data" not in content and openapi_declares_external_id(spec, content):
+                op["external_id"] = True
+            if details.get("deprecated") is True:
+                op["deprecated"] = ""
+            add_op(resource, op)
 
     return {
         "version": 1,
@@ -240,17 +276,19 @@ def build_from_postman(spec: Dict) -> Dict:
             params = collect_params(url, path)
             body = request.get("body") or {}
             has_body = method in {"POST", "PUT", "PATCH"} and body.get("mode") not in (None, "none")
-            add_op(
-                resource,
-                {
-                    "name": name,
-                    "method": method,
-                    "path": path,
-                    "description": item.get("description") or request.get("description"),
-                    "params": params,
-                    "has_body": has_body,
-                },
-            )
+            op = {
+                "name": name,
+                "method": method,
+                "path": path,
+                "description": item.get("description") or request.get("description"),
+                "params": params,
+                "has_body": has_body,
+            }
+            if has_body and body.get("mode") == "formdata":
+                op["body_kind"] = "multipart"
+            elif has_body and postman_declares_external_id(body):
+                op["external_id"] = True
+            add_op(resource, op)
 
     walk(spec.get("item") or [], [])
 
@@ -282,6 +320,9 @@ def merge_trees(trees: List[Dict]) -> Dict:
     for tree in trees:
         for res in tree.get("resources") or []:
             entry = resources.setdefault(res["name"], {"name": res["name"], "ops": []})
+            for tag in res.get("tags") or []:
+                if tag not in entry.get("tags", []):
+                    entry.setdefault("tags", []).append(tag)
             used_keys = seen_keys.setdefault(res["name"], set())
             used_names = seen_names.setdefault(res["name"], {op["name"] for op in entry["ops"]})
 